@@ -0,0 +1,127 @@
+//! Compares the byte level fill paths of `Display` with drawing pixel by pixel.
+//!
+//! The baseline is the pair of framebuffers `Display` used to draw into, pixel by pixel,
+//! before it had its own fill paths. Run on the host with
+//! `cargo bench --target x86_64-unknown-linux-gnu`.
+
+use core::convert::Infallible;
+use embedded_graphics::framebuffer::Framebuffer;
+use embedded_graphics::mono_font::ascii::FONT_6X9;
+use embedded_graphics::mono_font::MonoTextStyleBuilder;
+use embedded_graphics::text::Text;
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Point, Size};
+use embedded_graphics_core::pixelcolor::raw::{LittleEndian, RawU1};
+use embedded_graphics_core::pixelcolor::BinaryColor;
+use embedded_graphics_core::primitives::PointsIter;
+use embedded_graphics_core::{Drawable, Pixel};
+use epd_ssd1681::color::TriColor;
+use epd_ssd1681::graphics::{Display, DisplayRotation, BUFFER_SIZE};
+use epd_ssd1681::{HEIGHT, WIDTH};
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 200;
+
+type Plane = Framebuffer<
+    BinaryColor,
+    RawU1,
+    LittleEndian,
+    { WIDTH as usize },
+    { HEIGHT as usize },
+    BUFFER_SIZE,
+>;
+
+/// The original `Display` drawing, one pixel into each framebuffer at a time
+struct Baseline {
+    black: Plane,
+    red: Plane,
+}
+
+impl DrawTarget for Baseline {
+    type Color = TriColor;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            let (black, red) = match color {
+                TriColor::White => (BinaryColor::On, BinaryColor::Off),
+                TriColor::Black => (BinaryColor::Off, BinaryColor::Off),
+                TriColor::Red => (BinaryColor::On, BinaryColor::On),
+            };
+            self.black.draw_iter([Pixel(point, black)])?;
+            self.red.draw_iter([Pixel(point, red)])?;
+        }
+        Ok(())
+    }
+}
+
+impl OriginDimensions for Baseline {
+    fn size(&self) -> Size {
+        Size::new(WIDTH.into(), HEIGHT.into())
+    }
+}
+
+fn bench<D>(name: &str, target: &mut D, mut f: impl FnMut(&mut D)) {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f(black_box(&mut *target));
+    }
+    println!("{name:<40} {:>10.1?}/iter", start.elapsed() / ITERATIONS);
+}
+
+fn draw_all<D: DrawTarget<Color = TriColor, Error = Infallible>>(target: &mut D) {
+    let screen = target.bounding_box();
+    let colors = [TriColor::Red, TriColor::Black, TriColor::White];
+
+    bench("full screen, pixel by pixel", target, |target| {
+        let pixels = screen.points().zip(colors.into_iter().cycle());
+        target
+            .draw_iter(pixels.map(|(point, color)| Pixel(point, color)))
+            .unwrap();
+    });
+    bench("full screen, fill_solid", target, |target| {
+        target.fill_solid(&screen, TriColor::Red).unwrap();
+    });
+    bench("full screen, fill_contiguous", target, |target| {
+        target
+            .fill_contiguous(&screen, colors.into_iter().cycle())
+            .unwrap();
+    });
+    bench("clear", target, |target| {
+        target.clear(TriColor::White).unwrap();
+    });
+
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X9)
+        .text_color(TriColor::Black)
+        .background_color(TriColor::White)
+        .build();
+    bench("text", target, |target| {
+        Text::new(
+            "The quick brown fox\njumps over the lazy dog",
+            Point::new(5, 10),
+            style,
+        )
+        .draw(target)
+        .unwrap();
+    });
+}
+
+fn main() {
+    println!("baseline framebuffers");
+    draw_all(&mut Baseline {
+        black: Plane::new(),
+        red: Plane::new(),
+    });
+
+    for rotation in [DisplayRotation::Rotate0, DisplayRotation::Rotate90] {
+        let mut display = Display::new();
+        display.set_rotation(rotation);
+        println!("Display, {rotation:?}");
+        draw_all(&mut display);
+    }
+}
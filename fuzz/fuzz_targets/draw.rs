@@ -0,0 +1,136 @@
+//! Draws arbitrary primitives into a `Display` under every rotation and polarity.
+//!
+//! Run with `cargo +nightly fuzz run draw --target x86_64-unknown-linux-gnu` from the
+//! crate root.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use embedded_graphics::mono_font::ascii::FONT_6X10;
+use embedded_graphics::mono_font::MonoTextStyle;
+use embedded_graphics::prelude::*;
+use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle, Rectangle, Triangle};
+use embedded_graphics::text::Text;
+use epd_ssd1681::color::{Polarity, TriColor};
+use epd_ssd1681::config::PanelConfig;
+use epd_ssd1681::graphics::{Display, DisplayRotation};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Color {
+    Black,
+    White,
+    Red,
+}
+
+impl From<Color> for TriColor {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Black => TriColor::Black,
+            Color::White => TriColor::White,
+            Color::Red => TriColor::Red,
+        }
+    }
+}
+
+/// Shapes go through embedded-graphics, whose own arithmetic only holds for moderate
+/// coordinates, so they use `i16`. The fills reach `Display` directly and take any
+/// coordinates.
+#[derive(Arbitrary, Debug)]
+enum Shape {
+    Pixel(i32, i32, Color),
+    Line(i16, i16, i16, i16, u8, Color),
+    Rectangle(i16, i16, u16, u16, Color),
+    Circle(i16, i16, u16, Color),
+    Triangle([(i16, i16); 3], Color),
+    Text(i16, i16, String, Color),
+    FillSolid(i32, i32, u32, u32, Color),
+    FillContiguous(i32, i32, u32, u32, Vec<Color>),
+    Clear(Color),
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    rotation: u8,
+    hardware_rotation: bool,
+    bw_inverted: bool,
+    red_active_low: bool,
+    shapes: Vec<Shape>,
+}
+
+fn point(x: i16, y: i16) -> Point {
+    Point::new(x.into(), y.into())
+}
+
+fn draw(display: &mut Display, shape: Shape) {
+    let _ = match shape {
+        Shape::Pixel(x, y, color) => Pixel(Point::new(x, y), color.into()).draw(display),
+        Shape::Line(x0, y0, x1, y1, width, color) => Line::new(point(x0, y0), point(x1, y1))
+            .into_styled(PrimitiveStyle::with_stroke(color.into(), width.into()))
+            .draw(display),
+        Shape::Rectangle(x, y, width, height, color) => {
+            Rectangle::new(point(x, y), Size::new(width.into(), height.into()))
+                .into_styled(PrimitiveStyle::with_fill(color.into()))
+                .draw(display)
+        }
+        Shape::Circle(x, y, diameter, color) => Circle::new(point(x, y), diameter.into())
+            .into_styled(PrimitiveStyle::with_fill(color.into()))
+            .draw(display),
+        Shape::Triangle([a, b, c], color) => {
+            Triangle::new(point(a.0, a.1), point(b.0, b.1), point(c.0, c.1))
+                .into_styled(PrimitiveStyle::with_fill(color.into()))
+                .draw(display)
+        }
+        Shape::Text(x, y, text, color) => Text::new(
+            &text,
+            point(x, y),
+            MonoTextStyle::new(&FONT_6X10, color.into()),
+        )
+        .draw(display)
+        .map(drop),
+        Shape::FillSolid(x, y, width, height, color) => display.fill_solid(
+            &Rectangle::new(Point::new(x, y), Size::new(width, height)),
+            color.into(),
+        ),
+        Shape::FillContiguous(x, y, width, height, colors) => display.fill_contiguous(
+            &Rectangle::new(Point::new(x, y), Size::new(width, height)),
+            colors.into_iter().map(TriColor::from),
+        ),
+        Shape::Clear(color) => {
+            display.clear(color.into());
+            Ok(())
+        }
+    };
+}
+
+fuzz_target!(|input: Input| {
+    let mut display = Display::with_config(&PanelConfig {
+        polarity: Polarity {
+            bw_inverted: input.bw_inverted,
+            red_active_low: input.red_active_low,
+        },
+        ..PanelConfig::default()
+    });
+    display.set_rotation(match input.rotation % 4 {
+        0 => DisplayRotation::Rotate0,
+        1 => DisplayRotation::Rotate90,
+        2 => DisplayRotation::Rotate180,
+        _ => DisplayRotation::Rotate270,
+    });
+    display.set_hardware_rotation(input.hardware_rotation);
+    for shape in input.shapes {
+        draw(&mut display, shape);
+    }
+
+    let size = display.size();
+    for point in [
+        Point::zero(),
+        Point::new(size.width as i32 - 1, size.height as i32 - 1),
+    ] {
+        assert!(display.get_pixel(point).is_some());
+    }
+    assert!(display.get_pixel(Point::new(-1, 0)).is_none());
+    assert!(display
+        .get_pixel(Point::new(0, size.height as i32))
+        .is_none());
+});
@@ -0,0 +1,241 @@
+//! Convert a PNG or BMP image into the BW and red RAM buffers of a `Display`
+//!
+//! The image is drawn at the top left of a `Display`, with transparent pixels on white,
+//! and the buffers are written as Rust constants or as raw `.bin` files ready for
+//! `Ssd1681::update_bw_frame` and `Ssd1681::update_red_frame`.
+//!
+//! `cargo run --features cli --bin ssd1681-convert --target <host> -- [options] <image>`
+
+use embedded_graphics::draw_target::DrawTarget;
+use embedded_graphics::geometry::{OriginDimensions, Point, Size};
+use embedded_graphics::pixelcolor::Rgb888;
+use embedded_graphics::primitives::Rectangle;
+use epd_ssd1681::color::{Polarity, TriColor};
+use epd_ssd1681::config::PanelConfig;
+use epd_ssd1681::dither::{Dither, DitherMode};
+use epd_ssd1681::graphics::{Display, DisplayRotation};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::{env, fs};
+
+const USAGE: &str = "\
+usage: ssd1681-convert [options] <image>
+
+options:
+  --dither <none|floyd-steinberg|bayer>  dithering, floyd-steinberg by default
+  --rotation <0|90|180|270>              display rotation, 0 by default
+  --hardware-rotation                    leave mirroring to Ssd1681::set_rotation
+  --bw-inverted                          a set BW bit is black
+  --red-active-low                       a clear red bit is red
+  --format <rust|bin>                    output format, rust by default
+  --name <NAME>                          constant name prefix, from the image name by default
+  -o, --output <PATH>                    Rust file, or prefix of the <PATH>_black.bin and
+                                         <PATH>_red.bin files, stdout or the image name by default";
+
+#[derive(Debug, Default)]
+struct Options {
+    input: PathBuf,
+    dither: Option<DitherMode>,
+    rotation: DisplayRotation,
+    hardware_rotation: bool,
+    polarity: Polarity,
+    binary: bool,
+    name: Option<String>,
+    output: Option<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        dither: Some(DitherMode::FloydSteinberg),
+        ..Options::default()
+    };
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--dither" => {
+                options.dither = match value()?.as_str() {
+                    "none" => None,
+                    "floyd-steinberg" => Some(DitherMode::FloydSteinberg),
+                    "bayer" => Some(DitherMode::Bayer),
+                    other => return Err(format!("unknown dithering `{other}`")),
+                }
+            }
+            "--rotation" => {
+                options.rotation = match value()?.as_str() {
+                    "0" => DisplayRotation::Rotate0,
+                    "90" => DisplayRotation::Rotate90,
+                    "180" => DisplayRotation::Rotate180,
+                    "270" => DisplayRotation::Rotate270,
+                    other => return Err(format!("unknown rotation `{other}`")),
+                }
+            }
+            "--hardware-rotation" => options.hardware_rotation = true,
+            "--bw-inverted" => options.polarity.bw_inverted = true,
+            "--red-active-low" => options.polarity.red_active_low = true,
+            "--format" => {
+                options.binary = match value()?.as_str() {
+                    "rust" => false,
+                    "bin" => true,
+                    other => return Err(format!("unknown format `{other}`")),
+                }
+            }
+            "--name" => options.name = Some(value()?),
+            "-o" | "--output" => options.output = Some(value()?.into()),
+            "-h" | "--help" => return Err(USAGE.into()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`\n{USAGE}")),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("more than one image given\n{USAGE}")),
+        }
+    }
+    options.input = input.ok_or_else(|| USAGE.to_string())?;
+    Ok(options)
+}
+
+/// Draw the image onto a display set up as the options say
+fn convert(image: &image::RgbaImage, options: &Options) -> Display {
+    let mut display = Display::with_config(&PanelConfig {
+        polarity: options.polarity,
+        ..PanelConfig::default()
+    });
+    display.set_rotation(options.rotation);
+    display.set_hardware_rotation(options.hardware_rotation);
+    display.clear(TriColor::White);
+
+    let area = Rectangle::new(Point::zero(), Size::new(image.width(), image.height()));
+    let colors = image.pixels().map(|pixel| {
+        // blend transparent pixels with the white background
+        let [r, g, b, a] = pixel.0.map(u32::from);
+        let blend = |channel: u32| ((channel * a + 255 * (255 - a)) / 255) as u8;
+        Rgb888::new(blend(r), blend(g), blend(b))
+    });
+    let result = match options.dither {
+        Some(mode) => Dither::new(&mut display, mode).fill_contiguous(&area, colors),
+        None => display.fill_contiguous(&area, colors.map(TriColor::from)),
+    };
+    result.unwrap();
+    display
+}
+
+/// Rust source with one constant per buffer
+fn rust_consts(name: &str, source: &Path, black: &[u8], red: &[u8]) -> String {
+    let mut out = format!(
+        "// Generated by ssd1681-convert from {}\n",
+        source.display()
+    );
+    for (suffix, data) in [("BLACK", black), ("RED", red)] {
+        let _ = writeln!(out, "\npub const {name}_{suffix}: [u8; {}] = [", data.len());
+        for line in data.chunks(16) {
+            let bytes: Vec<String> = line.iter().map(|byte| format!("0x{byte:02X},")).collect();
+            let _ = writeln!(out, "    {}", bytes.join(" "));
+        }
+        out.push_str("];\n");
+    }
+    out
+}
+
+/// Constant name prefix from a file name, e.g. `SPLASH_SCREEN` for `splash-screen.png`
+fn const_name(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        format!("IMAGE_{name}")
+    }
+}
+
+fn run(options: Options) -> Result<(), String> {
+    let image = image::open(&options.input)
+        .map_err(|err| format!("cannot read {}: {err}", options.input.display()))?
+        .to_rgba8();
+    let display = convert(&image, &options);
+    let size = display.size();
+    if image.width() > size.width || image.height() > size.height {
+        eprintln!(
+            "warning: the {}x{} image is cropped to {}x{}",
+            image.width(),
+            image.height(),
+            size.width,
+            size.height
+        );
+    }
+    let (black, red) = (display.black_data(), display.red_data());
+    let write = |path: &Path, data: &[u8]| {
+        fs::write(path, data).map_err(|err| format!("cannot write {}: {err}", path.display()))
+    };
+    if options.binary {
+        let prefix = options
+            .output
+            .clone()
+            .unwrap_or_else(|| options.input.with_extension(""));
+        let path = |plane: &str| {
+            let mut name = prefix.clone().into_os_string();
+            name.push(format!("_{plane}.bin"));
+            PathBuf::from(name)
+        };
+        write(&path("black"), black)?;
+        write(&path("red"), red)
+    } else {
+        let name = options
+            .name
+            .clone()
+            .unwrap_or_else(|| const_name(&options.input));
+        let source = rust_consts(&name, &options.input, black, red);
+        match &options.output {
+            Some(path) => write(path, source.as_bytes()),
+            None => {
+                print!("{source}");
+                Ok(())
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match parse_args(env::args().skip(1)).and_then(run) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transparent_pixels_are_white() {
+        let mut image = image::RgbaImage::new(8, 1);
+        image.put_pixel(0, 0, image::Rgba([0, 0, 0, 255]));
+        image.put_pixel(1, 0, image::Rgba([255, 0, 0, 255]));
+        let options = parse_args(["--dither", "none", "x.png"].map(String::from).into_iter());
+        let display = convert(&image, &options.unwrap());
+        assert_eq!(0x7F, display.black_data()[0]);
+        assert_eq!(0x40, display.red_data()[0]);
+    }
+
+    #[test]
+    fn writes_rust_consts() {
+        assert_eq!(
+            "SPLASH_SCREEN",
+            const_name(Path::new("art/splash-screen.png"))
+        );
+        assert_eq!("IMAGE_1", const_name(Path::new("1.bmp")));
+        let source = rust_consts("LOGO", Path::new("logo.png"), &[0xFF; 17], &[0x00; 1]);
+        assert!(source.contains("pub const LOGO_BLACK: [u8; 17] = [\n    0xFF,"));
+        assert!(source.contains("pub const LOGO_RED: [u8; 1] = [\n    0x00,\n];"));
+    }
+}
@@ -0,0 +1,83 @@
+//! Decode a captured SSD1681 command stream
+//!
+//! Reads a CSV file, or stdin, with one SPI byte per row, e.g. exported from sigrok or
+//! PulseView after decoding MOSI. The header row names the columns: the one called `dc`
+//! holds the level of DC (`0`/`1` or `low`/`high`) and the one called `mosi` or `data`
+//! the byte in hex. Other columns, such as time stamps, are ignored.
+//!
+//! `cargo run --features std --bin ssd1681-decode --target <host> -- capture.csv`
+
+use epd_ssd1681::decode::Decoder;
+use std::io::{self, BufRead, BufReader};
+use std::process::ExitCode;
+use std::{env, fs};
+
+fn main() -> ExitCode {
+    let input: Box<dyn BufRead> = match env::args().nth(1) {
+        Some(path) => match fs::File::open(&path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(err) => {
+                eprintln!("cannot open {path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+        None => Box::new(io::stdin().lock()),
+    };
+    match read_csv(input) {
+        Ok(bytes) => {
+            for operation in Decoder::new(bytes) {
+                println!("{operation}");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Bytes of the capture, each with DC
+fn read_csv(input: impl BufRead) -> Result<Vec<(bool, u8)>, String> {
+    let mut lines = input.lines().enumerate();
+    let (dc, mosi) = loop {
+        let Some((_, line)) = lines.next() else {
+            return Err("no header row".into());
+        };
+        let line = line.map_err(|err| err.to_string())?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let columns: Vec<String> = split(&line).map(str::to_ascii_lowercase).collect();
+        let find = |names: &[&str]| columns.iter().position(|c| names.contains(&c.as_str()));
+        match (find(&["dc"]), find(&["mosi", "data"])) {
+            (Some(dc), Some(mosi)) => break (dc, mosi),
+            _ => return Err("the header needs a `dc` and a `mosi` or `data` column".into()),
+        }
+    };
+
+    let mut bytes = Vec::new();
+    for (number, line) in lines {
+        let line = line.map_err(|err| err.to_string())?;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = split(&line).collect();
+        let parse = || -> Option<(bool, u8)> {
+            let dc = match fields.get(dc)?.to_ascii_lowercase().as_str() {
+                "1" | "high" | "h" => true,
+                "0" | "low" | "l" => false,
+                _ => return None,
+            };
+            let byte = fields.get(mosi)?;
+            let byte = byte.strip_prefix("0x").unwrap_or(byte);
+            Some((dc, u8::from_str_radix(byte, 16).ok()?))
+        };
+        bytes.push(parse().ok_or_else(|| format!("line {}: cannot parse `{line}`", number + 1))?);
+    }
+    Ok(bytes)
+}
+
+fn split(line: &str) -> impl Iterator<Item = &str> {
+    line.split(',').map(|field| field.trim().trim_matches('"'))
+}
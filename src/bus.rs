@@ -0,0 +1,230 @@
+//! SPI device, pins and delay shared by the host-side test doubles
+//!
+//! The handles turn what the driver does to its peripherals into calls on a [`Device`]:
+//! bytes written over SPI become commands or data by the level of DC, pin changes and
+//! delays are passed on, and BUSY reads what the device says. [`Mock`] records these
+//! calls and [`Emulator`] decodes them into controller state.
+//!
+//! Cloning a handle shares the device, so a test can keep inspecting it while the
+//! driver owns the handles.
+//!
+//! [`Mock`]: crate::mock::Mock
+//! [`Emulator`]: crate::emulator::Emulator
+
+use core::convert::Infallible;
+use core::fmt;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{self, InputPin, OutputPin};
+use embedded_hal::spi::{self, Operation, SpiDevice};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Output lines of the interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Line {
+    Cs,
+    Dc,
+    Rst,
+}
+
+/// What sits on the other end of the bus
+pub trait Device: fmt::Debug {
+    /// Byte written over SPI while DC was low
+    fn command(&mut self, command: u8);
+
+    /// Byte written over SPI while DC was high
+    fn data(&mut self, byte: u8);
+
+    /// CS, DC or RST set high (`true`) or low
+    fn pin(&mut self, _line: Line, _high: bool) {}
+
+    /// Whether BUSY reads high now, idle unless overridden
+    fn busy(&mut self) -> bool {
+        false
+    }
+
+    /// Time passed in a delay, in nanoseconds
+    fn delay(&mut self, _ns: u32) {}
+}
+
+/// A device and the level of DC the bytes written to it are split by
+#[derive(Debug)]
+pub struct Bus<D: ?Sized> {
+    dc_high: bool,
+    pub device: D,
+}
+
+impl<D> Bus<D> {
+    /// Bus with DC low, shared between the device owner and the handles
+    pub fn shared(device: D) -> Rc<RefCell<Bus<D>>> {
+        Rc::new(RefCell::new(Bus {
+            dc_high: false,
+            device,
+        }))
+    }
+}
+
+impl<D: Device + ?Sized> Bus<D> {
+    fn write(&mut self, words: &[u8]) {
+        for &byte in words {
+            if self.dc_high {
+                self.device.data(byte);
+            } else {
+                self.device.command(byte);
+            }
+        }
+    }
+}
+
+type Shared = Rc<RefCell<Bus<dyn Device>>>;
+
+/// SPI device, CS, BUSY, DC and RST in the order `Ssd1681::new` takes them
+pub fn interface<D: Device + 'static>(bus: &Rc<RefCell<Bus<D>>>) -> (Spi, Pin, Busy, Pin, Pin) {
+    let bus: Shared = bus.clone();
+    (
+        Spi(bus.clone()),
+        Pin(bus.clone(), Line::Cs),
+        Busy(bus.clone()),
+        Pin(bus.clone(), Line::Dc),
+        Pin(bus, Line::Rst),
+    )
+}
+
+/// Delay that is passed to the device instead of slept
+pub fn delay<D: Device + 'static>(bus: &Rc<RefCell<Bus<D>>>) -> Delay {
+    Delay(bus.clone())
+}
+
+/// SPI device that writes to a [`Device`], reads return zeros
+#[derive(Clone, Debug)]
+pub struct Spi(Shared);
+
+impl spi::ErrorType for Spi {
+    type Error = Infallible;
+}
+
+impl SpiDevice for Spi {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let mut bus = self.0.borrow_mut();
+        for operation in operations {
+            match operation {
+                Operation::Write(words) => bus.write(words),
+                Operation::Transfer(read, write) => {
+                    bus.write(write);
+                    read.fill(0);
+                }
+                Operation::TransferInPlace(words) => {
+                    bus.write(words);
+                    words.fill(0);
+                }
+                Operation::Read(words) => words.fill(0),
+                Operation::DelayNs(ns) => bus.device.delay(*ns),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// CS, DC or RST pin of a [`Device`]
+#[derive(Clone, Debug)]
+pub struct Pin(Shared, Line);
+
+impl Pin {
+    fn set(&mut self, high: bool) {
+        let mut bus = self.0.borrow_mut();
+        if self.1 == Line::Dc {
+            bus.dc_high = high;
+        }
+        bus.device.pin(self.1, high);
+    }
+}
+
+impl digital::ErrorType for Pin {
+    type Error = Infallible;
+}
+
+impl OutputPin for Pin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set(true);
+        Ok(())
+    }
+}
+
+/// BUSY pin of a [`Device`]
+#[derive(Clone, Debug)]
+pub struct Busy(Shared);
+
+impl digital::ErrorType for Busy {
+    type Error = Infallible;
+}
+
+impl InputPin for Busy {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.0.borrow_mut().device.busy())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+/// Delay of a [`Device`] that passes the time on instead of sleeping
+#[derive(Clone, Debug)]
+pub struct Delay(Shared);
+
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.borrow_mut().device.delay(ns);
+    }
+}
+
+/// Helpers shared by the driver, graphics and emulator tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::{Busy, Delay, Pin, Spi};
+    use crate::color::TriColor;
+    use crate::driver::Ssd1681;
+    use crate::graphics::{Display, DisplayRotation};
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::{Line, PrimitiveStyle, Rectangle};
+
+    pub(crate) type Driver = Ssd1681<Spi, Pin, Busy, Pin, Pin>;
+
+    pub(crate) const ROTATIONS: [DisplayRotation; 4] = [
+        DisplayRotation::Rotate0,
+        DisplayRotation::Rotate90,
+        DisplayRotation::Rotate180,
+        DisplayRotation::Rotate270,
+    ];
+
+    /// Driver initialized over `interface`, e.g. of a `Mock` or an `Emulator`
+    pub(crate) fn start(interface: (Spi, Pin, Busy, Pin, Pin), delay: &mut Delay) -> Driver {
+        let (spi, cs, busy, dc, rst) = interface;
+        Ssd1681::new(spi, cs, busy, dc, rst, delay).unwrap()
+    }
+
+    /// White display with a black box and a red line, neither of them symmetric
+    pub(crate) fn draw_pattern(display: &mut Display) {
+        display.clear(TriColor::White);
+        Rectangle::new(Point::new(10, 20), Size::new(30, 40))
+            .into_styled(PrimitiveStyle::with_fill(TriColor::Black))
+            .draw(display)
+            .unwrap();
+        Line::new(Point::new(5, 190), Point::new(199, 3))
+            .into_styled(PrimitiveStyle::with_stroke(TriColor::Red, 1))
+            .draw(display)
+            .unwrap();
+    }
+
+    /// Send both buffers of `display` and refresh
+    pub(crate) fn show(driver: &mut Driver, display: &Display, delay: &mut Delay) {
+        driver.update_bw_frame(display.black_data()).unwrap();
+        driver.update_red_frame(display.red_data()).unwrap();
+        driver.display_frame(delay).unwrap();
+    }
+}
@@ -48,4 +48,59 @@ impl Cmd {
     pub const SET_RAMXCOUNT: u8 = 0x4E;
     pub const SET_RAMYCOUNT: u8 = 0x4F;
     pub const NOP: u8 = 0xFF;
+
+    /// Name of a command byte, as spelled by the constant above
+    pub fn name(command: u8) -> Option<&'static str> {
+        Some(match command {
+            Cmd::DRIVER_CONTROL => "DRIVER_CONTROL",
+            Cmd::GATE_VOLTAGE => "GATE_VOLTAGE",
+            Cmd::SOURCE_VOLTAGE => "SOURCE_VOLTAGE",
+            Cmd::INIT_SETTING => "INIT_SETTING",
+            Cmd::INIT_WRITE_REG => "INIT_WRITE_REG",
+            Cmd::INIT_READ_REG => "INIT_READ_REG",
+            Cmd::BOOSTER_SOFT_START => "BOOSTER_SOFT_START",
+            Cmd::DEEP_SLEEP => "DEEP_SLEEP",
+            Cmd::DATA_MODE => "DATA_MODE",
+            Cmd::SW_RESET => "SW_RESET",
+            Cmd::HV_DETECT => "HV_DETECT",
+            Cmd::VCI_DETECT => "VCI_DETECT",
+            Cmd::TEMP_CONTROL => "TEMP_CONTROL",
+            Cmd::TEMP_WRITE => "TEMP_WRITE",
+            Cmd::TEMP_READ => "TEMP_READ",
+            Cmd::EXTTEMP_WRITE => "EXTTEMP_WRITE",
+            Cmd::MASTER_ACTIVATE => "MASTER_ACTIVATE",
+            Cmd::DISP_CTRL1 => "DISP_CTRL1",
+            Cmd::DISP_CTRL2 => "DISP_CTRL2",
+            Cmd::WRITE_BWRAM => "WRITE_BWRAM",
+            Cmd::WRITE_REDRAM => "WRITE_REDRAM",
+            Cmd::READ_RAM => "READ_RAM",
+            Cmd::VCOM_SENSE => "VCOM_SENSE",
+            Cmd::VCOM_DURATION => "VCOM_DURATION",
+            Cmd::WRITE_VCOM_OTP => "WRITE_VCOM_OTP",
+            Cmd::WRITE_VCOM_CTRL => "WRITE_VCOM_CTRL",
+            Cmd::WRITE_VCOM_REG => "WRITE_VCOM_REG",
+            Cmd::READ_OTP => "READ_OTP",
+            Cmd::READ_USERID => "READ_USERID",
+            Cmd::READ_STATUS => "READ_STATUS",
+            Cmd::WRITE_WS_OTP => "WRITE_WS_OTP",
+            Cmd::LOAD_WS_OTP => "LOAD_WS_OTP",
+            Cmd::WRITE_LUT => "WRITE_LUT",
+            Cmd::CRC_CALC => "CRC_CALC",
+            Cmd::CRC_READ => "CRC_READ",
+            Cmd::PROG_OTP => "PROG_OTP",
+            Cmd::WRITE_DISPLAY_OPT => "WRITE_DISPLAY_OPT",
+            Cmd::WRITE_USERID => "WRITE_USERID",
+            Cmd::OTP_PROGMODE => "OTP_PROGMODE",
+            Cmd::WRITE_BORDER => "WRITE_BORDER",
+            Cmd::END_OPTION => "END_OPTION",
+            Cmd::SET_RAMXPOS => "SET_RAMXPOS",
+            Cmd::SET_RAMYPOS => "SET_RAMYPOS",
+            Cmd::AUTOWRITE_RED => "AUTOWRITE_RED",
+            Cmd::AUTOWRITE_BW => "AUTOWRITE_BW",
+            Cmd::SET_RAMXCOUNT => "SET_RAMXCOUNT",
+            Cmd::SET_RAMYCOUNT => "SET_RAMYCOUNT",
+            Cmd::NOP => "NOP",
+            _ => return None,
+        })
+    }
 }
@@ -1,6 +1,15 @@
-use embedded_graphics_core::pixelcolor::{BinaryColor, PixelColor};
+use core::fmt;
+use core::str::FromStr;
+use embedded_graphics::mock_display::ColorMapping;
+use embedded_graphics_core::pixelcolor::raw::{RawData, RawU2};
+use embedded_graphics_core::pixelcolor::{
+    BinaryColor, Gray8, GrayColor, PixelColor, Rgb565, Rgb888, RgbColor,
+};
 
 /// Color with 3 states.
+///
+/// The raw representation is 2 bits wide: 0 for black, 1 for white and 2 for red, so
+/// tri-color sprites can be stored as `ImageRaw<TriColor>`.
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub enum TriColor {
     Black,
@@ -9,20 +18,111 @@ pub enum TriColor {
 }
 
 impl PixelColor for TriColor {
-    type Raw = ();
+    type Raw = RawU2;
 }
 
-impl From<u8> for TriColor {
-    fn from(value: u8) -> Self {
-        match value {
+/// The unused raw value 3 reads as white
+impl From<RawU2> for TriColor {
+    fn from(raw: RawU2) -> Self {
+        match raw.into_inner() {
             0 => TriColor::Black,
-            1 => TriColor::White,
             2 => TriColor::Red,
-            _ => panic!("invalid color value"),
+            _ => TriColor::White,
+        }
+    }
+}
+
+impl From<TriColor> for RawU2 {
+    fn from(c: TriColor) -> Self {
+        RawU2::new(match c {
+            TriColor::Black => 0,
+            TriColor::White => 1,
+            TriColor::Red => 2,
+        })
+    }
+}
+
+/// The raw value of a color.
+///
+/// Deprecated, to be removed in the next release: use `RawU2::from` instead, or
+/// `Polarity::encode` for the RAM bits of a color.
+impl From<TriColor> for u8 {
+    fn from(c: TriColor) -> u8 {
+        RawU2::from(c).into_inner()
+    }
+}
+
+/// `B`, `W` and `R` in `MockDisplay` patterns
+impl ColorMapping for TriColor {
+    fn char_to_color(c: char) -> Self {
+        TriColor::try_from(c).unwrap_or_else(|_| panic!("Invalid char in pattern: '{}'", c))
+    }
+
+    fn color_to_char(color: Self) -> char {
+        match color {
+            TriColor::Black => 'B',
+            TriColor::White => 'W',
+            TriColor::Red => 'R',
+        }
+    }
+}
+
+/// Error from turning a value, char or string into a [`TriColor`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseColorError;
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("not a color, expected black, white or red")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseColorError {}
+
+impl TryFrom<u8> for TriColor {
+    type Error = ParseColorError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TriColor::Black),
+            1 => Ok(TriColor::White),
+            2 => Ok(TriColor::Red),
+            _ => Err(ParseColorError),
+        }
+    }
+}
+
+/// `B`, `W` or `R`, in either case
+impl TryFrom<char> for TriColor {
+    type Error = ParseColorError;
+
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c.to_ascii_uppercase() {
+            'B' => Ok(TriColor::Black),
+            'W' => Ok(TriColor::White),
+            'R' => Ok(TriColor::Red),
+            _ => Err(ParseColorError),
         }
     }
 }
 
+/// A single color char or `black`, `white` or `red`, in either case
+impl FromStr for TriColor {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return TriColor::try_from(c);
+        }
+        [TriColor::Black, TriColor::White, TriColor::Red]
+            .into_iter()
+            .find(|color| s.eq_ignore_ascii_case(color.name()))
+            .ok_or(ParseColorError)
+    }
+}
+
 impl From<BinaryColor> for TriColor {
     fn from(b: BinaryColor) -> TriColor {
         match b {
@@ -32,11 +132,187 @@ impl From<BinaryColor> for TriColor {
     }
 }
 
-impl From<TriColor> for u8 {
-    fn from(c: TriColor) -> u8 {
+impl TriColor {
+    fn name(&self) -> &'static str {
+        match self {
+            TriColor::Black => "black",
+            TriColor::White => "white",
+            TriColor::Red => "red",
+        }
+    }
+
+    /// Palette color nearest to an RGB value, channels may lie outside 0..=255
+    pub(crate) fn nearest(rgb: [i32; 3]) -> TriColor {
+        [TriColor::Black, TriColor::White, TriColor::Red]
+            .into_iter()
+            .min_by_key(|&color| {
+                let palette = Rgb888::from(color);
+                let palette = [palette.r(), palette.g(), palette.b()];
+                rgb.iter()
+                    .zip(palette)
+                    .map(|(&channel, p)| (channel - i32::from(p)).pow(2))
+                    .sum::<i32>()
+            })
+            .unwrap_or(TriColor::White)
+    }
+}
+
+impl From<TriColor> for Rgb888 {
+    fn from(c: TriColor) -> Rgb888 {
+        match c {
+            TriColor::Black => Rgb888::BLACK,
+            TriColor::White => Rgb888::WHITE,
+            TriColor::Red => Rgb888::RED,
+        }
+    }
+}
+
+/// Nearest palette color
+impl From<Rgb888> for TriColor {
+    fn from(c: Rgb888) -> TriColor {
+        TriColor::nearest([c.r().into(), c.g().into(), c.b().into()])
+    }
+}
+
+/// Nearest palette color
+impl From<Rgb565> for TriColor {
+    fn from(c: Rgb565) -> TriColor {
+        Rgb888::from(c).into()
+    }
+}
+
+/// Black or white, whichever is nearer
+impl From<Gray8> for TriColor {
+    fn from(c: Gray8) -> TriColor {
+        if c.luma() < 0x80 {
+            TriColor::Black
+        } else {
+            TriColor::White
+        }
+    }
+}
+
+/// Color of black, white and yellow panels.
+///
+/// Yellow is stored like red, so drawing on a `Display` works through
+/// `DrawTargetExt::color_converted`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum BwyColor {
+    Black,
+    White,
+    Yellow,
+}
+
+impl PixelColor for BwyColor {
+    type Raw = RawU2;
+}
+
+/// The unused raw value 3 reads as white
+impl From<RawU2> for BwyColor {
+    fn from(raw: RawU2) -> Self {
+        TriColor::from(raw).into()
+    }
+}
+
+impl From<BwyColor> for RawU2 {
+    fn from(c: BwyColor) -> Self {
+        TriColor::from(c).into()
+    }
+}
+
+impl From<BwyColor> for TriColor {
+    fn from(c: BwyColor) -> TriColor {
+        match c {
+            BwyColor::Black => TriColor::Black,
+            BwyColor::White => TriColor::White,
+            BwyColor::Yellow => TriColor::Red,
+        }
+    }
+}
+
+impl From<TriColor> for BwyColor {
+    fn from(c: TriColor) -> BwyColor {
         match c {
-            TriColor::White => 0xFF,
-            TriColor::Black | TriColor::Red => 0x00,
+            TriColor::Black => BwyColor::Black,
+            TriColor::White => BwyColor::White,
+            TriColor::Red => BwyColor::Yellow,
+        }
+    }
+}
+
+impl From<BwyColor> for Rgb888 {
+    fn from(c: BwyColor) -> Rgb888 {
+        match c {
+            BwyColor::Black => Rgb888::BLACK,
+            BwyColor::White => Rgb888::WHITE,
+            BwyColor::Yellow => Rgb888::YELLOW,
+        }
+    }
+}
+
+/// Bits of one pixel in the BW and red RAM.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct PlaneBits {
+    pub bw: bool,
+    pub red: bool,
+}
+
+impl PlaneBits {
+    /// BW RAM byte for 8 pixels of these bits
+    pub fn bw_byte(&self) -> u8 {
+        if self.bw {
+            0xFF
+        } else {
+            0x00
+        }
+    }
+
+    /// Red RAM byte for 8 pixels of these bits
+    pub fn red_byte(&self) -> u8 {
+        if self.red {
+            0xFF
+        } else {
+            0x00
+        }
+    }
+}
+
+/// How a panel reads its RAM, the single place colors are turned into RAM bits.
+///
+/// Panels differ in which value of a red (or yellow) RAM bit shows the color. With the
+/// default polarity a set BW bit is white and a clear one black, and a set red
+/// bit shows red whatever the BW bit is. Red pixels keep the BW bit of white, so they
+/// turn white rather than black when the red RAM is bypassed.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct Polarity {
+    /// A set BW bit is black
+    pub bw_inverted: bool,
+    /// A clear red bit is red
+    pub red_active_low: bool,
+}
+
+impl Polarity {
+    /// RAM bits of a color
+    pub fn encode(&self, color: TriColor) -> PlaneBits {
+        let (white, red) = match color {
+            TriColor::Black => (false, false),
+            TriColor::White => (true, false),
+            TriColor::Red => (true, true),
+        };
+        PlaneBits {
+            bw: white != self.bw_inverted,
+            red: red != self.red_active_low,
+        }
+    }
+
+    /// Color the panel shows for RAM bits
+    pub fn decode(&self, bits: PlaneBits) -> TriColor {
+        if bits.red != self.red_active_low {
+            TriColor::Red
+        } else if bits.bw != self.bw_inverted {
+            TriColor::White
+        } else {
+            TriColor::Black
         }
     }
 }
@@ -46,9 +322,85 @@ mod tests {
     use super::*;
 
     #[test]
-    fn from_u8() {
-        assert_eq!(TriColor::Black, TriColor::from(0u8));
-        assert_eq!(TriColor::White, TriColor::from(1u8));
-        assert_eq!(TriColor::Red, TriColor::from(2u8));
+    fn u8_is_the_raw_value() {
+        for color in [TriColor::Black, TriColor::White, TriColor::Red] {
+            assert_eq!(Ok(color), TriColor::try_from(u8::from(color)));
+        }
+    }
+
+    #[test]
+    fn try_from_u8() {
+        assert_eq!(TriColor::Black, TriColor::try_from(0u8).unwrap());
+        assert_eq!(TriColor::White, TriColor::try_from(1u8).unwrap());
+        assert_eq!(TriColor::Red, TriColor::try_from(2u8).unwrap());
+        assert_eq!(Err(ParseColorError), TriColor::try_from(3u8));
+        assert!(TriColor::try_from(0xFFu8).is_err());
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(TriColor::Black, TriColor::try_from('b').unwrap());
+        assert_eq!(TriColor::Red, TriColor::try_from('R').unwrap());
+        assert!(TriColor::try_from('x').is_err());
+        assert_eq!(TriColor::White, "W".parse().unwrap());
+        assert_eq!(TriColor::Red, "Red".parse().unwrap());
+        assert_eq!(TriColor::Black, "BLACK".parse().unwrap());
+        assert!("".parse::<TriColor>().is_err());
+        assert_eq!(Err(ParseColorError), "blue".parse::<TriColor>());
+    }
+
+    #[test]
+    fn polarity_round_trip() {
+        for bw_inverted in [false, true] {
+            for red_active_low in [false, true] {
+                let polarity = Polarity {
+                    bw_inverted,
+                    red_active_low,
+                };
+                for color in [TriColor::Black, TriColor::White, TriColor::Red] {
+                    assert_eq!(color, polarity.decode(polarity.encode(color)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn default_polarity() {
+        let polarity = Polarity::default();
+        let red = polarity.encode(TriColor::Red);
+        assert_eq!((0xFF, 0xFF), (red.bw_byte(), red.red_byte()));
+        let black = polarity.encode(TriColor::Black);
+        assert_eq!((0x00, 0x00), (black.bw_byte(), black.red_byte()));
+        // a bypassed red RAM reads as 0
+        let bypassed = PlaneBits { red: false, ..red };
+        assert_eq!(TriColor::White, polarity.decode(bypassed));
+    }
+
+    #[test]
+    fn yellow() {
+        assert_eq!(TriColor::Red, TriColor::from(BwyColor::Yellow));
+        assert_eq!(BwyColor::Yellow, BwyColor::from(TriColor::Red));
+        assert_eq!(RawU2::new(2), RawU2::from(BwyColor::Yellow));
+        assert_eq!(Rgb888::YELLOW, Rgb888::from(BwyColor::Yellow));
+    }
+
+    #[test]
+    fn raw() {
+        for color in [TriColor::Black, TriColor::White, TriColor::Red] {
+            assert_eq!(color, TriColor::from(RawU2::from(color)));
+        }
+        assert_eq!(TriColor::Red, TriColor::from(RawU2::new(2)));
+        assert_eq!(TriColor::White, TriColor::from(RawU2::new(3)));
+    }
+
+    #[test]
+    fn from_rgb() {
+        assert_eq!(TriColor::Black, TriColor::from(Rgb888::new(40, 30, 20)));
+        assert_eq!(TriColor::White, TriColor::from(Rgb888::new(230, 200, 210)));
+        assert_eq!(TriColor::Red, TriColor::from(Rgb888::new(200, 40, 60)));
+        assert_eq!(TriColor::Red, TriColor::from(Rgb565::RED));
+        assert_eq!(TriColor::White, TriColor::from(Rgb565::YELLOW));
+        assert_eq!(TriColor::Black, TriColor::from(Gray8::new(0x7F)));
+        assert_eq!(TriColor::White, TriColor::from(Gray8::new(0x80)));
     }
 }
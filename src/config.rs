@@ -0,0 +1,388 @@
+//! Panel configuration applied by the driver during `init`
+use crate::color::Polarity;
+use crate::flag::Flag;
+use crate::interface::DisplayError;
+
+/// Gate driving voltage (VGH), sent with `Cmd::GATE_VOLTAGE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GateVoltage(u8);
+
+impl GateVoltage {
+    /// Gate voltage from 10V to 20V in 0.5V steps
+    pub fn from_millivolts(mv: u16) -> Result<Self, DisplayError> {
+        if !(10_000..=20_000).contains(&mv) || !mv.is_multiple_of(500) {
+            return Err(DisplayError::InvalidConfigError);
+        }
+        Ok(GateVoltage(0x03 + ((mv - 10_000) / 500) as u8))
+    }
+
+    /// Value of the register byte
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Positive source driving voltage (VSH1 or VSH2).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vsh(u8);
+
+impl Vsh {
+    /// Source voltage from 2.4V to 8.8V in 0.1V steps or from 9V to 17V in 0.2V steps
+    pub fn from_millivolts(mv: u16) -> Result<Self, DisplayError> {
+        match mv {
+            2_400..=8_800 if mv.is_multiple_of(100) => Ok(Vsh(0x8E + ((mv - 2_400) / 100) as u8)),
+            9_000..=17_000 if mv.is_multiple_of(200) => Ok(Vsh(0x23 + ((mv - 9_000) / 200) as u8)),
+            _ => Err(DisplayError::InvalidConfigError),
+        }
+    }
+
+    /// Value of the register byte
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Negative source driving voltage (VSL).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vsl(u8);
+
+impl Vsl {
+    /// Source voltage from -9V to -17V in 0.5V steps
+    pub fn from_millivolts(mv: i16) -> Result<Self, DisplayError> {
+        if !(-17_000..=-9_000).contains(&mv) || mv % 500 != 0 {
+            return Err(DisplayError::InvalidConfigError);
+        }
+        Ok(Vsl(0x1A + ((-mv - 9_000) / 250) as u8))
+    }
+
+    /// Value of the register byte
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Source driving voltages, sent with `Cmd::SOURCE_VOLTAGE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceVoltage {
+    pub vsh1: Vsh,
+    pub vsh2: Vsh,
+    pub vsl: Vsl,
+}
+
+impl SourceVoltage {
+    pub(crate) fn data(&self) -> [u8; 3] {
+        [self.vsh1.value(), self.vsh2.value(), self.vsl.value()]
+    }
+}
+
+/// Duration of one booster soft start phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PhaseDuration {
+    Ms10 = 0b00,
+    Ms20 = 0b01,
+    Ms30 = 0b10,
+    #[default]
+    Ms40 = 0b11,
+}
+
+/// Driving strength and minimum off time of the booster in one soft start phase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoosterPhase(u8);
+
+impl BoosterPhase {
+    /// `strength` ranges from 1 (weakest) to 8 (strongest), `min_off_time` is the datasheet
+    /// code from 0x4 (2.6us) to 0xF (16.5us).
+    pub fn new(strength: u8, min_off_time: u8) -> Result<Self, DisplayError> {
+        if !(1..=8).contains(&strength) || !(0x4..=0xF).contains(&min_off_time) {
+            return Err(DisplayError::InvalidConfigError);
+        }
+        Ok(BoosterPhase(0x80 | ((strength - 1) << 4) | min_off_time))
+    }
+
+    /// Value of the register byte
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+/// Booster soft start phases, sent with `Cmd::BOOSTER_SOFT_START`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoosterSoftStart {
+    pub phases: [BoosterPhase; 3],
+    pub durations: [PhaseDuration; 3],
+}
+
+impl BoosterSoftStart {
+    pub(crate) fn data(&self) -> [u8; 4] {
+        let [d1, d2, d3] = self.durations;
+        [
+            self.phases[0].value(),
+            self.phases[1].value(),
+            self.phases[2].value(),
+            ((d3 as u8) << 4) | ((d2 as u8) << 2) | d1 as u8,
+        ]
+    }
+}
+
+/// Waveform driven on the panel border, sent with `Cmd::WRITE_BORDER`.
+///
+/// Which fixed level shows as black, white or red depends on the panel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BorderWaveform {
+    /// Fixed level VSS
+    FixVss,
+    /// Fixed level VSH1
+    FixVsh1,
+    /// Fixed level VSL
+    FixVsl,
+    /// Fixed level VSH2
+    FixVsh2,
+    /// Follow LUT0
+    FollowLut0,
+    /// Follow LUT1
+    #[default]
+    FollowLut1,
+    /// Follow LUT2
+    FollowLut2,
+    /// Follow LUT3
+    FollowLut3,
+    /// Follow VCOM
+    Vcom,
+    /// Floating border
+    HiZ,
+}
+
+impl From<BorderWaveform> for u8 {
+    fn from(border: BorderWaveform) -> u8 {
+        match border {
+            BorderWaveform::FixVss => Flag::BORDER_WAVEFORM_FIX_LEVEL | Flag::BORDER_WAVEFORM_VSS,
+            BorderWaveform::FixVsh1 => Flag::BORDER_WAVEFORM_FIX_LEVEL | Flag::BORDER_WAVEFORM_VSH1,
+            BorderWaveform::FixVsl => Flag::BORDER_WAVEFORM_FIX_LEVEL | Flag::BORDER_WAVEFORM_VSL,
+            BorderWaveform::FixVsh2 => Flag::BORDER_WAVEFORM_FIX_LEVEL | Flag::BORDER_WAVEFORM_VSH2,
+            BorderWaveform::FollowLut0 => {
+                Flag::BORDER_WAVEFORM_GS_TRANSITION
+                    | Flag::BORDER_WAVEFORM_GS_FOLLOW_LUT
+                    | Flag::BORDER_WAVEFORM_LUT0
+            }
+            BorderWaveform::FollowLut1 => {
+                Flag::BORDER_WAVEFORM_GS_TRANSITION
+                    | Flag::BORDER_WAVEFORM_GS_FOLLOW_LUT
+                    | Flag::BORDER_WAVEFORM_LUT1
+            }
+            BorderWaveform::FollowLut2 => {
+                Flag::BORDER_WAVEFORM_GS_TRANSITION
+                    | Flag::BORDER_WAVEFORM_GS_FOLLOW_LUT
+                    | Flag::BORDER_WAVEFORM_LUT2
+            }
+            BorderWaveform::FollowLut3 => {
+                Flag::BORDER_WAVEFORM_GS_TRANSITION
+                    | Flag::BORDER_WAVEFORM_GS_FOLLOW_LUT
+                    | Flag::BORDER_WAVEFORM_LUT3
+            }
+            BorderWaveform::Vcom => Flag::BORDER_WAVEFORM_VCOM,
+            BorderWaveform::HiZ => Flag::BORDER_WAVEFORM_HIZ,
+        }
+    }
+}
+
+/// Order in which RAM addresses are written, sent with `Cmd::DATA_MODE`.
+///
+/// Variants name the Y and X address directions, the `YFirst` variants update the Y
+/// address after each byte instead of the X address.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DataEntryMode {
+    DecrYDecrX = Flag::DATA_ENTRY_DECRY_DECRX,
+    DecrYIncrX = Flag::DATA_ENTRY_DECRY_INCRX,
+    IncrYDecrX = Flag::DATA_ENTRY_INCRY_DECRX,
+    #[default]
+    IncrYIncrX = Flag::DATA_ENTRY_INCRY_INCRX,
+    DecrYDecrXYFirst = Flag::DATA_ENTRY_Y_FIRST | Flag::DATA_ENTRY_DECRY_DECRX,
+    DecrYIncrXYFirst = Flag::DATA_ENTRY_Y_FIRST | Flag::DATA_ENTRY_DECRY_INCRX,
+    IncrYDecrXYFirst = Flag::DATA_ENTRY_Y_FIRST | Flag::DATA_ENTRY_INCRY_DECRX,
+    IncrYIncrXYFirst = Flag::DATA_ENTRY_Y_FIRST | Flag::DATA_ENTRY_INCRY_INCRX,
+}
+
+impl DataEntryMode {
+    /// The X address counts up
+    pub fn x_increments(&self) -> bool {
+        *self as u8 & Flag::DATA_ENTRY_DECRY_INCRX != 0
+    }
+
+    /// The Y address counts up
+    pub fn y_increments(&self) -> bool {
+        *self as u8 & Flag::DATA_ENTRY_INCRY_DECRX != 0
+    }
+
+    /// The Y address is updated after each byte
+    pub fn y_first(&self) -> bool {
+        *self as u8 & Flag::DATA_ENTRY_Y_FIRST != 0
+    }
+}
+
+impl From<DataEntryMode> for u8 {
+    fn from(mode: DataEntryMode) -> u8 {
+        mode as u8
+    }
+}
+
+/// Gate scan settings, the last byte of `Cmd::DRIVER_CONTROL`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GateScan {
+    /// Start scanning at G1 instead of G0, swapping each pair of gates (GD)
+    pub start_at_g1: bool,
+    /// Scan even gates before odd gates instead of interlacing them (SM)
+    pub even_then_odd: bool,
+    /// Scan from the last gate to G0, mirroring the panel vertically (TB)
+    pub reverse: bool,
+}
+
+impl From<GateScan> for u8 {
+    fn from(scan: GateScan) -> u8 {
+        let mut value = 0;
+        if scan.start_at_g1 {
+            value |= Flag::DRIVER_CONTROL_GD;
+        }
+        if scan.even_then_odd {
+            value |= Flag::DRIVER_CONTROL_SM;
+        }
+        if scan.reverse {
+            value |= Flag::DRIVER_CONTROL_TB;
+        }
+        value
+    }
+}
+
+/// How a RAM plane is used when the display refreshes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RamOption {
+    /// Use the RAM content as written
+    #[default]
+    Normal,
+    /// Ignore the RAM content and read every pixel as 0
+    Bypass,
+    /// Invert the RAM content
+    Inverse,
+}
+
+/// Source outputs driven by the controller.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SourceOutputMode {
+    /// All sources, S0 to S199
+    #[default]
+    All,
+    /// Only S8 to S167, for panels with fewer columns
+    S8ToS167,
+}
+
+/// RAM options used at refresh time, sent with `Cmd::DISP_CTRL1`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DisplayControl {
+    pub bw: RamOption,
+    pub red: RamOption,
+    pub source_output: SourceOutputMode,
+}
+
+impl DisplayControl {
+    pub(crate) fn data(&self) -> [u8; 2] {
+        let bw = match self.bw {
+            RamOption::Normal => 0,
+            RamOption::Bypass => Flag::DISP_CTRL1_BYPASS_BW,
+            RamOption::Inverse => Flag::DISP_CTRL1_INVERSE_BW,
+        };
+        let red = match self.red {
+            RamOption::Normal => 0,
+            RamOption::Bypass => Flag::DISP_CTRL1_BYPASS_RED,
+            RamOption::Inverse => Flag::DISP_CTRL1_INVERSE_RED,
+        };
+        let source_output = match self.source_output {
+            SourceOutputMode::All => 0,
+            SourceOutputMode::S8ToS167 => Flag::DISP_CTRL1_SOURCE_S8_S167,
+        };
+        [red | bw, source_output]
+    }
+}
+
+/// Panel specific settings. Settings left as `None` keep the controller defaults.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PanelConfig {
+    pub gate_voltage: Option<GateVoltage>,
+    pub source_voltage: Option<SourceVoltage>,
+    pub booster: Option<BoosterSoftStart>,
+    pub border: BorderWaveform,
+    pub data_entry_mode: DataEntryMode,
+    pub gate_scan: GateScan,
+    pub display_control: DisplayControl,
+    pub polarity: Polarity,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gate_voltage() {
+        assert_eq!(0x03, GateVoltage::from_millivolts(10_000).unwrap().value());
+        assert_eq!(0x17, GateVoltage::from_millivolts(20_000).unwrap().value());
+        assert!(GateVoltage::from_millivolts(9_500).is_err());
+        assert!(GateVoltage::from_millivolts(15_200).is_err());
+    }
+
+    #[test]
+    fn source_voltage() {
+        assert_eq!(0x8E, Vsh::from_millivolts(2_400).unwrap().value());
+        assert_eq!(0xCE, Vsh::from_millivolts(8_800).unwrap().value());
+        assert_eq!(0x41, Vsh::from_millivolts(15_000).unwrap().value());
+        assert!(Vsh::from_millivolts(8_900).is_err());
+        assert!(Vsh::from_millivolts(9_100).is_err());
+        assert_eq!(0x1A, Vsl::from_millivolts(-9_000).unwrap().value());
+        assert_eq!(0x3A, Vsl::from_millivolts(-17_000).unwrap().value());
+        assert!(Vsl::from_millivolts(9_000).is_err());
+    }
+
+    #[test]
+    fn booster() {
+        let phase = BoosterPhase::new(1, 0xB).unwrap();
+        let booster = BoosterSoftStart {
+            phases: [
+                phase,
+                BoosterPhase::new(2, 0xC).unwrap(),
+                BoosterPhase::new(2, 0x6).unwrap(),
+            ],
+            durations: [PhaseDuration::Ms40; 3],
+        };
+        assert_eq!([0x8B, 0x9C, 0x96, 0x3F], booster.data());
+        assert!(BoosterPhase::new(0, 0xB).is_err());
+        assert!(BoosterPhase::new(1, 0x3).is_err());
+    }
+
+    #[test]
+    fn border() {
+        assert_eq!(0x05, u8::from(BorderWaveform::default()));
+        assert_eq!(0x50, u8::from(BorderWaveform::FixVsh1));
+        assert_eq!(0x07, u8::from(BorderWaveform::FollowLut3));
+        assert_eq!(0xC0, u8::from(BorderWaveform::HiZ));
+    }
+
+    #[test]
+    fn display_control() {
+        assert_eq!([0x00, 0x00], DisplayControl::default().data());
+        let night_mode = DisplayControl {
+            bw: RamOption::Inverse,
+            red: RamOption::Bypass,
+            source_output: SourceOutputMode::S8ToS167,
+        };
+        assert_eq!([0x48, 0x80], night_mode.data());
+    }
+
+    #[test]
+    fn data_entry_mode() {
+        assert_eq!(0x03, u8::from(DataEntryMode::default()));
+        assert_eq!(0x06, u8::from(DataEntryMode::IncrYDecrXYFirst));
+        assert!(DataEntryMode::DecrYIncrXYFirst.x_increments());
+        assert!(!DataEntryMode::DecrYIncrXYFirst.y_increments());
+        assert!(!DataEntryMode::IncrYDecrX.x_increments());
+        assert!(DataEntryMode::IncrYDecrX.y_increments());
+    }
+}
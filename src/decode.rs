@@ -0,0 +1,304 @@
+//! Decoder for captured SSD1681 command streams
+//!
+//! [`Decoder`] groups bytes captured on the bus, each with the level of DC, into
+//! [`Operation`]s: a command and the data sent after it. Operations print as the command
+//! name from [`Cmd`] and its decoded parameters, e.g. `SET_RAMXPOS x 0..=199` or
+//! `WRITE_BWRAM 5000 bytes`.
+
+use crate::cmd::Cmd;
+use crate::flag::Flag;
+use core::fmt;
+use core::iter::Peekable;
+
+/// Number of data bytes kept for decoding, longer data is only counted
+pub const MAX_PARAMS: usize = 8;
+
+/// A command and the data sent after it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Operation {
+    /// Command byte, `None` for data captured before the first command
+    pub command: Option<u8>,
+    params: [u8; MAX_PARAMS],
+    len: usize,
+}
+
+impl Operation {
+    /// First `MAX_PARAMS` data bytes
+    pub fn params(&self) -> &[u8] {
+        &self.params[..self.len.min(MAX_PARAMS)]
+    }
+
+    /// Number of data bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no data was sent after the command
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn push(&mut self, byte: u8) {
+        if let Some(param) = self.params.get_mut(self.len) {
+            *param = byte;
+        }
+        self.len += 1;
+    }
+
+    /// Parameters as hex, with the number of bytes if they don't all fit
+    fn fmt_params(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.params() {
+            write!(f, " {byte:02X}")?;
+        }
+        if self.len > MAX_PARAMS {
+            write!(f, " ... ({} bytes)", self.len)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(command) = self.command else {
+            f.write_str("data without command")?;
+            return self.fmt_params(f);
+        };
+        match Cmd::name(command) {
+            Some(name) => f.write_str(name)?,
+            None => write!(f, "0x{command:02X}")?,
+        }
+        let p = self.params();
+        match (command, p) {
+            (Cmd::DRIVER_CONTROL, &[low, high, scan]) => {
+                write!(f, " {} gates", u16::from_le_bytes([low, high & 0x01]) + 1)?;
+                fmt_flags(
+                    f,
+                    scan,
+                    &[
+                        (Flag::DRIVER_CONTROL_GD, "GD"),
+                        (Flag::DRIVER_CONTROL_SM, "SM"),
+                        (Flag::DRIVER_CONTROL_TB, "TB"),
+                    ],
+                )
+            }
+            (Cmd::DATA_MODE, &[mode]) => write!(
+                f,
+                " x{} y{}{}",
+                if mode & 0x01 != 0 { '+' } else { '-' },
+                if mode & 0x02 != 0 { '+' } else { '-' },
+                if mode & Flag::DATA_ENTRY_Y_FIRST != 0 {
+                    " y first"
+                } else {
+                    ""
+                },
+            ),
+            (Cmd::SET_RAMXPOS, &[start, end]) => write!(
+                f,
+                " x {}..={}",
+                u16::from(start & 0x3F) * 8,
+                u16::from(end & 0x3F) * 8 + 7
+            ),
+            (Cmd::SET_RAMYPOS, &[start_low, start_high, end_low, end_high]) => write!(
+                f,
+                " y {}..={}",
+                u16::from_le_bytes([start_low, start_high & 0x01]),
+                u16::from_le_bytes([end_low, end_high & 0x01])
+            ),
+            (Cmd::SET_RAMXCOUNT, &[x]) => write!(f, " x {}", u16::from(x & 0x3F) * 8),
+            (Cmd::SET_RAMYCOUNT, &[low, high]) => {
+                write!(f, " y {}", u16::from_le_bytes([low, high & 0x01]))
+            }
+            (Cmd::DISP_CTRL1, &[ram, ..]) => {
+                write!(
+                    f,
+                    " bw {} red {}",
+                    ram_option(ram & 0x0F),
+                    ram_option(ram >> 4)
+                )?;
+                if p.get(1)
+                    .is_some_and(|s| s & Flag::DISP_CTRL1_SOURCE_S8_S167 != 0)
+                {
+                    f.write_str(" S8 to S167")?;
+                }
+                Ok(())
+            }
+            (Cmd::DISP_CTRL2, &[sequence]) => {
+                write!(f, " {sequence:02X}")?;
+                fmt_flags(
+                    f,
+                    sequence,
+                    &[
+                        (0x80, "clock on"),
+                        (0x40, "analog on"),
+                        (0x20, "load temperature"),
+                        (0x10, "load LUT"),
+                        (0x08, "mode 2"),
+                        (0x04, "display"),
+                        (0x02, "analog off"),
+                        (0x01, "clock off"),
+                    ],
+                )
+            }
+            (Cmd::WRITE_BORDER, &[border]) => {
+                write!(f, " {border:02X} ")?;
+                match border & 0xC0 {
+                    Flag::BORDER_WAVEFORM_VCOM => f.write_str("VCOM"),
+                    Flag::BORDER_WAVEFORM_HIZ => f.write_str("HiZ"),
+                    Flag::BORDER_WAVEFORM_FIX_LEVEL => {
+                        let level = ["VSS", "VSH1", "VSL", "VSH2"][usize::from(border >> 4 & 0x03)];
+                        write!(f, "fix {level}")
+                    }
+                    _ => write!(f, "follow LUT{}", border & 0x03),
+                }
+            }
+            (Cmd::TEMP_CONTROL, &[sensor]) => {
+                f.write_str(if sensor == Flag::INTERNAL_TEMP_SENSOR {
+                    " internal sensor"
+                } else {
+                    " external sensor"
+                })
+            }
+            (Cmd::WRITE_BWRAM | Cmd::WRITE_REDRAM | Cmd::WRITE_LUT, _) => {
+                write!(f, " {} bytes", self.len)
+            }
+            _ => self.fmt_params(f),
+        }
+    }
+}
+
+fn ram_option(option: u8) -> &'static str {
+    match option & 0x0C {
+        0x04 => "bypass",
+        0x08 => "inverse",
+        _ => "normal",
+    }
+}
+
+/// Names of the set bits of a flags byte, in brackets
+fn fmt_flags(f: &mut fmt::Formatter<'_>, value: u8, flags: &[(u8, &str)]) -> fmt::Result {
+    f.write_str(" [")?;
+    let mut names = flags.iter().filter(|(bit, _)| value & bit != 0);
+    if let Some((_, name)) = names.next() {
+        f.write_str(name)?;
+    }
+    for (_, name) in names {
+        write!(f, ", {name}")?;
+    }
+    f.write_str("]")
+}
+
+/// Iterator of the operations in captured bytes, each paired with DC (`true` for data)
+pub struct Decoder<I: Iterator<Item = (bool, u8)>> {
+    bytes: Peekable<I>,
+}
+
+impl<I: Iterator<Item = (bool, u8)>> Decoder<I> {
+    /// Decoder of `bytes`, each paired with DC
+    pub fn new(bytes: impl IntoIterator<IntoIter = I>) -> Self {
+        Decoder {
+            bytes: bytes.into_iter().peekable(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = (bool, u8)>> Iterator for Decoder<I> {
+    type Item = Operation;
+
+    fn next(&mut self) -> Option<Operation> {
+        let (data, byte) = self.bytes.next()?;
+        let mut operation = Operation {
+            command: None,
+            params: [0; MAX_PARAMS],
+            len: 0,
+        };
+        if data {
+            operation.push(byte);
+        } else {
+            operation.command = Some(byte);
+        }
+        while let Some((_, byte)) = self.bytes.next_if(|&(data, _)| data) {
+            operation.push(byte);
+        }
+        Some(operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::Ssd1681;
+    use crate::mock::Mock;
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+
+    fn decode(bytes: &[(bool, u8)]) -> Vec<String> {
+        Decoder::new(bytes.iter().copied())
+            .map(|operation| operation.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn decodes_init_sequence() {
+        let mock = Mock::new();
+        let (spi, cs, busy, dc, rst) = mock.interface();
+        Ssd1681::new(spi, cs, busy, dc, rst, &mut mock.delay()).unwrap();
+        let operations: Vec<String> = mock.operations().iter().map(|o| o.to_string()).collect();
+
+        assert_eq!(
+            [
+                "SW_RESET",
+                "DRIVER_CONTROL 200 gates []",
+                "DATA_MODE x+ y+",
+                "SET_RAMXPOS x 0..=199",
+                "SET_RAMYPOS y 0..=199",
+                "SET_RAMXCOUNT x 0",
+                "SET_RAMYCOUNT y 0",
+                "WRITE_BORDER 05 follow LUT1",
+                "TEMP_CONTROL internal sensor",
+            ],
+            &operations[..]
+        );
+    }
+
+    #[test]
+    fn decodes_parameters() {
+        assert_eq!(
+            [
+                "DISP_CTRL2 F7 [clock on, analog on, load temperature, load LUT, display, analog off, clock off]",
+                "MASTER_ACTIVATE",
+                "DISP_CTRL1 bw normal red bypass",
+                "DATA_MODE x- y+ y first",
+                "WRITE_BORDER C0 HiZ",
+            ],
+            &decode(&[
+                (false, Cmd::DISP_CTRL2),
+                (true, 0xF7),
+                (false, Cmd::MASTER_ACTIVATE),
+                (false, Cmd::DISP_CTRL1),
+                (true, 0x40),
+                (true, 0x00),
+                (false, Cmd::DATA_MODE),
+                (true, 0x06),
+                (false, Cmd::WRITE_BORDER),
+                (true, 0xC0),
+            ])[..]
+        );
+    }
+
+    #[test]
+    fn long_and_unknown_data() {
+        let mut bytes = std::vec![(true, 0x01), (false, 0x7F), (true, 0xAB)];
+        bytes.push((false, Cmd::WRITE_REDRAM));
+        bytes.extend([(true, 0x00); 5000]);
+        bytes.extend([(false, Cmd::GATE_VOLTAGE), (true, 0x17)]);
+        assert_eq!(
+            [
+                "data without command 01",
+                "0x7F AB",
+                "WRITE_REDRAM 5000 bytes",
+                "GATE_VOLTAGE 17",
+            ],
+            &decode(&bytes)[..]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Dithering of full color drawings onto the tri-color palette
+use crate::color::TriColor;
+use crate::{HEIGHT, WIDTH};
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Point, Size};
+use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics_core::Pixel;
+
+/// Widest row that error diffusion keeps track of
+const MAX_WIDTH: usize = if WIDTH > HEIGHT { WIDTH } else { HEIGHT } as usize;
+
+/// Pixels quantized before they are passed on to the target
+const BATCH: usize = 16;
+
+/// 4x4 Bayer threshold matrix
+const BAYER: [[i32; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Dithering algorithm used by `Dither`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DitherMode {
+    /// Floyd–Steinberg error diffusion, expects pixels in row order like images draw them
+    #[default]
+    FloydSteinberg,
+    /// Ordered dithering with a 4x4 Bayer matrix, works in any drawing order
+    Bayer,
+}
+
+/// Draw target that dithers `Rgb888` drawings onto a `TriColor` target, e.g. a `Display`.
+///
+/// Other color types can be drawn through `DrawTargetExt::color_converted`.
+pub struct Dither<'a, D> {
+    target: &'a mut D,
+    mode: DitherMode,
+    /// Error diffused into the current and next row, in sixteenths. Kept as `i16` so the
+    /// rows take 2.4 KB rather than 4.8 KB on small targets.
+    errors: [[[i16; 3]; MAX_WIDTH]; 2],
+    row: Option<i32>,
+}
+
+impl<'a, D> Dither<'a, D>
+where
+    D: DrawTarget<Color = TriColor>,
+{
+    /// Dither drawings onto `target` with `mode`
+    pub fn new(target: &'a mut D, mode: DitherMode) -> Self {
+        Dither {
+            target,
+            mode,
+            errors: [[[0; 3]; MAX_WIDTH]; 2],
+            row: None,
+        }
+    }
+
+    fn quantize(&mut self, Pixel(point, color): Pixel<Rgb888>) -> Pixel<TriColor> {
+        let rgb = [color.r(), color.g(), color.b()].map(i32::from);
+        let quantized = match self.mode {
+            DitherMode::Bayer => {
+                let threshold =
+                    BAYER[point.y.rem_euclid(4) as usize][point.x.rem_euclid(4) as usize];
+                let offset = (threshold * 2 + 1) * 8 - 128;
+                TriColor::nearest(rgb.map(|channel| channel + offset))
+            }
+            DitherMode::FloydSteinberg => self.diffuse(point.x, point.y, rgb),
+        };
+        Pixel(point, quantized)
+    }
+
+    fn diffuse(&mut self, x: i32, y: i32, rgb: [i32; 3]) -> TriColor {
+        match self.row {
+            Some(row) if row == y => {}
+            Some(row) if row + 1 == y => {
+                self.errors[0] = self.errors[1];
+                self.errors[1].fill([0; 3]);
+            }
+            _ => self.errors.iter_mut().for_each(|row| row.fill([0; 3])),
+        }
+        self.row = Some(y);
+
+        let Some(column) = usize::try_from(x).ok().filter(|&x| x < MAX_WIDTH) else {
+            return TriColor::nearest(rgb);
+        };
+        let mut wanted = rgb;
+        for (channel, error) in wanted.iter_mut().zip(self.errors[0][column]) {
+            *channel += i32::from(error) / 16;
+        }
+        let color = TriColor::nearest(wanted);
+        let palette = Rgb888::from(color);
+        let palette = [palette.r(), palette.g(), palette.b()].map(i32::from);
+
+        for channel in 0..3 {
+            let error = wanted[channel] - palette[channel];
+            if column + 1 < MAX_WIDTH {
+                spread(&mut self.errors[0][column + 1][channel], error * 7);
+                spread(&mut self.errors[1][column + 1][channel], error);
+            }
+            if column > 0 {
+                spread(&mut self.errors[1][column - 1][channel], error * 3);
+            }
+            spread(&mut self.errors[1][column][channel], error * 5);
+        }
+        color
+    }
+}
+
+/// Add to a diffused error, saturating instead of wrapping
+fn spread(cell: &mut i16, error: i32) {
+    *cell = (i32::from(*cell) + error).clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+}
+
+impl<D> DrawTarget for Dither<'_, D>
+where
+    D: DrawTarget<Color = TriColor>,
+{
+    type Color = Rgb888;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        // quantize in batches to keep the calls into the target down
+        let mut batch = [Pixel(Point::zero(), TriColor::White); BATCH];
+        let mut len = 0;
+        for pixel in pixels.into_iter() {
+            batch[len] = self.quantize(pixel);
+            len += 1;
+            if len == batch.len() {
+                self.target.draw_iter(batch)?;
+                len = 0;
+            }
+        }
+        self.target.draw_iter(batch.into_iter().take(len))
+    }
+}
+
+impl<D> OriginDimensions for Dither<'_, D>
+where
+    D: DrawTarget<Color = TriColor>,
+{
+    fn size(&self) -> Size {
+        self.target.bounding_box().size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::Display;
+    use embedded_graphics_core::primitives::Rectangle;
+
+    fn black_pixels(mode: DitherMode, color: Rgb888) -> usize {
+        let mut display = Display::new();
+        display.clear(TriColor::White);
+        let area = Rectangle::new(Point::zero(), Size::new(16, 16));
+        Dither::new(&mut display, mode)
+            .fill_solid(&area, color)
+            .unwrap();
+        display
+            .black_data()
+            .chunks(25)
+            .take(16)
+            .map(|row| (row[0].count_zeros() + row[1].count_zeros()) as usize)
+            .sum()
+    }
+
+    #[test]
+    fn mid_gray_is_half_black() {
+        let gray = Rgb888::new(128, 128, 128);
+        assert_eq!(128, black_pixels(DitherMode::Bayer, gray));
+        let black = black_pixels(DitherMode::FloydSteinberg, gray);
+        assert!((120..=136).contains(&black), "{black}");
+    }
+
+    #[test]
+    fn palette_colors_are_kept() {
+        for mode in [DitherMode::Bayer, DitherMode::FloydSteinberg] {
+            assert_eq!(0, black_pixels(mode, Rgb888::WHITE));
+            assert_eq!(256, black_pixels(mode, Rgb888::BLACK));
+            assert_eq!(0, black_pixels(mode, Rgb888::RED));
+        }
+    }
+
+    #[test]
+    fn error_rows_fit_small_stacks() {
+        assert!(core::mem::size_of::<Dither<'_, Display>>() <= 2_500);
+    }
+}
@@ -1,15 +1,97 @@
 use crate::cmd::Cmd;
-use crate::color::TriColor::{Red, White};
+use crate::color::TriColor;
+use crate::config::{
+    BorderWaveform, DataEntryMode, DisplayControl, GateScan, PanelConfig, RamOption,
+};
 use crate::flag::Flag;
+#[cfg(feature = "frame-diff")]
+use crate::graphics::BUFFER_SIZE;
+use crate::graphics::{visible, DisplayRotation};
 use crate::interface::{DisplayError, DisplayInterface};
+use crate::lut::{GRAY4, LUT_SIZE};
 use crate::{HEIGHT, WIDTH};
+use embedded_graphics_core::geometry::{Point, Size};
+use embedded_graphics_core::primitives::Rectangle;
 use embedded_hal::delay::DelayNs;
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal::spi::SpiDevice;
 
+const BYTES_PER_ROW: usize = WIDTH as usize / 8;
+
+/// Row hashes of the frames last written to the BW and red RAM.
+///
+/// The hashes are only kept with the `frame-diff` feature.
+struct FrameHistory {
+    enabled: bool,
+    #[cfg(feature = "frame-diff")]
+    rows: [[u32; HEIGHT as usize]; 2],
+    #[cfg(feature = "frame-diff")]
+    valid: [bool; 2],
+    /// RAM changed since the last refresh
+    changed: bool,
+}
+
+impl FrameHistory {
+    const BW: usize = 0;
+    const RED: usize = 1;
+
+    fn new() -> Self {
+        FrameHistory {
+            enabled: false,
+            #[cfg(feature = "frame-diff")]
+            rows: [[0; HEIGHT as usize]; 2],
+            #[cfg(feature = "frame-diff")]
+            valid: [false; 2],
+            changed: true,
+        }
+    }
+
+    /// RAM was written without comparing it to the history
+    fn invalidate(&mut self, plane: usize) {
+        #[cfg(feature = "frame-diff")]
+        {
+            self.valid[plane] = false;
+        }
+        #[cfg(not(feature = "frame-diff"))]
+        let _ = plane;
+        self.changed = true;
+    }
+
+    /// Record the rows of a new frame, returning the first and last changed row.
+    ///
+    /// Frames must be `BUFFER_SIZE` bytes long.
+    #[cfg(feature = "frame-diff")]
+    fn update(&mut self, plane: usize, buffer: &[u8]) -> Option<(u32, u32)> {
+        let mut changed: Option<(u32, u32)> = None;
+        for (y, row) in buffer.chunks_exact(BYTES_PER_ROW).enumerate() {
+            let hash = row_hash(row);
+            if !self.valid[plane] || self.rows[plane][y] != hash {
+                self.rows[plane][y] = hash;
+                let y = y as u32;
+                changed = Some(changed.map_or((y, y), |(first, _)| (first, y)));
+            }
+        }
+        self.valid[plane] = true;
+        self.changed |= changed.is_some();
+        changed
+    }
+}
+
+/// FNV-1a hash of one row of a frame
+#[cfg(feature = "frame-diff")]
+fn row_hash(row: &[u8]) -> u32 {
+    row.iter().fold(0x811C_9DC5, |hash, &byte| {
+        (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+    })
+}
+
 /// A configured display with a hardware interface.
 pub struct Ssd1681<SPI, CS, BUSY, DC, RST> {
     interface: DisplayInterface<SPI, CS, BUSY, DC, RST>,
+    config: PanelConfig,
+    history: FrameHistory,
+    /// The red RAM holds the white frame of `clear_red_frame`
+    red_cleared: bool,
 }
 
 impl<SPI, CS, BUSY, DC, RST> Ssd1681<SPI, CS, BUSY, DC, RST>
@@ -29,33 +111,59 @@ where
         rst: RST,
         delay: &mut impl DelayNs,
     ) -> Result<Self, DisplayError>
+    where
+        Self: Sized,
+    {
+        Self::with_config(spi, cs, busy, dc, rst, PanelConfig::default(), delay)
+    }
+
+    /// Create and initialize the display driver for a panel with non-default settings
+    pub fn with_config(
+        spi: SPI,
+        cs: CS,
+        busy: BUSY,
+        dc: DC,
+        rst: RST,
+        config: PanelConfig,
+        delay: &mut impl DelayNs,
+    ) -> Result<Self, DisplayError>
     where
         Self: Sized,
     {
         let interface = DisplayInterface::new(spi, cs, busy, dc, rst);
-        let mut ssd1681 = Ssd1681 { interface };
+        let mut ssd1681 = Ssd1681 {
+            interface,
+            config,
+            history: FrameHistory::new(),
+            red_cleared: false,
+        };
         ssd1681.init(delay)?;
         Ok(ssd1681)
     }
 
     /// Initialise the controller
     pub fn init(&mut self, delay: &mut impl DelayNs) -> Result<(), DisplayError> {
+        self.history.invalidate(FrameHistory::BW);
+        self.history.invalidate(FrameHistory::RED);
+        self.red_cleared = false;
         self.interface.reset(delay);
         self.interface.cmd(Cmd::SW_RESET)?;
         self.interface.wait_until_idle(delay);
 
-        self.interface
-            .cmd_with_data(Cmd::DRIVER_CONTROL, &[(HEIGHT - 1), 0x00, 0x00])?;
+        self.interface.cmd_with_data(
+            Cmd::DRIVER_CONTROL,
+            &[(HEIGHT - 1), 0x00, self.config.gate_scan.into()],
+        )?;
+
+        self.apply_config()?;
 
         self.interface
-            .cmd_with_data(Cmd::DATA_MODE, &[Flag::DATA_ENTRY_INCRY_INCRX])?;
+            .cmd_with_data(Cmd::DATA_MODE, &[self.config.data_entry_mode.into()])?;
 
         self.use_full_frame()?;
 
-        self.interface.cmd_with_data(
-            Cmd::WRITE_BORDER,
-            &[Flag::BORDER_WAVEFORM_FOLLOW_LUT | Flag::BORDER_WAVEFORM_LUT1],
-        )?;
+        self.interface
+            .cmd_with_data(Cmd::WRITE_BORDER, &[self.config.border.into()])?;
 
         self.interface
             .cmd_with_data(Cmd::TEMP_CONTROL, &[Flag::INTERNAL_TEMP_SENSOR])?;
@@ -64,34 +172,181 @@ where
         Ok(())
     }
 
+    /// Settings the driver was created with, as changed by its setters since.
+    ///
+    /// Create buffers with `Display::with_config` from this so they encode colors the
+    /// way the panel reads them.
+    pub fn config(&self) -> &PanelConfig {
+        &self.config
+    }
+
+    /// Set the waveform driven on the panel border, shown on the next refresh
+    pub fn set_border(&mut self, border: BorderWaveform) -> Result<(), DisplayError> {
+        self.config.border = border;
+        self.history.changed = true;
+        self.interface
+            .cmd_with_data(Cmd::WRITE_BORDER, &[border.into()])
+    }
+
+    /// Set the order in which frame buffers are written into RAM
+    pub fn set_data_entry_mode(&mut self, mode: DataEntryMode) -> Result<(), DisplayError> {
+        self.config.data_entry_mode = mode;
+        // the same buffer lands differently in RAM now
+        self.history.invalidate(FrameHistory::BW);
+        self.history.invalidate(FrameHistory::RED);
+        self.interface.cmd_with_data(Cmd::DATA_MODE, &[mode.into()])
+    }
+
+    /// Let the controller mirror buffers drawn with `Display::set_hardware_rotation` enabled
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) -> Result<(), DisplayError> {
+        self.set_data_entry_mode(match rotation {
+            DisplayRotation::Rotate0 => DataEntryMode::IncrYIncrX,
+            DisplayRotation::Rotate90 => DataEntryMode::IncrYDecrX,
+            DisplayRotation::Rotate180 => DataEntryMode::DecrYDecrX,
+            DisplayRotation::Rotate270 => DataEntryMode::DecrYIncrX,
+        })
+    }
+
+    /// Set the gate scan order, e.g. to mirror the panel vertically
+    pub fn set_gate_scan(&mut self, scan: GateScan) -> Result<(), DisplayError> {
+        self.config.gate_scan = scan;
+        self.history.changed = true;
+        self.interface
+            .cmd_with_data(Cmd::DRIVER_CONTROL, &[(HEIGHT - 1), 0x00, scan.into()])
+    }
+
+    /// Invert or bypass the RAM planes on the next refresh, without re-sending buffers
+    pub fn set_display_control(&mut self, control: DisplayControl) -> Result<(), DisplayError> {
+        self.config.display_control = control;
+        self.history.changed = true;
+        self.interface
+            .cmd_with_data(Cmd::DISP_CTRL1, &control.data())
+    }
+
     /// Update the whole BW buffer on the display driver
     pub fn update_bw_frame(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
-        self.use_full_frame()?;
-        self.interface.cmd_with_data(Cmd::WRITE_BWRAM, buffer)
+        self.update_frame(FrameHistory::BW, Cmd::WRITE_BWRAM, buffer)
     }
 
     /// Update the whole Red buffer on the display driver
     pub fn update_red_frame(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
-        self.use_full_frame()?;
-        self.interface.cmd_with_data(Cmd::WRITE_REDRAM, buffer)
+        self.red_cleared = false;
+        self.update_frame(FrameHistory::RED, Cmd::WRITE_REDRAM, buffer)
+    }
+
+    /// Compare frames to the ones last sent by `update_bw_frame` and `update_red_frame`.
+    ///
+    /// Only the band of changed rows is then transferred, and `display_frame` skips the
+    /// refresh when neither the RAM nor a setting changed since the last one. Frames must
+    /// then be `BUFFER_SIZE` bytes long. Rows are compared by hash, and the hashes take
+    /// 1.6 KB of every driver built with the `frame-diff` feature.
+    #[cfg(feature = "frame-diff")]
+    pub fn set_frame_diff(&mut self, enabled: bool) {
+        self.history.enabled = enabled;
+        self.history.invalidate(FrameHistory::BW);
+        self.history.invalidate(FrameHistory::RED);
+    }
+
+    /// Whether the RAM changed since the last refresh, always true without frame diffing
+    pub fn frame_changed(&self) -> bool {
+        !self.history.enabled || self.history.changed
+    }
+
+    /// Update part of the BW buffer, e.g. a box of a `DirtyRegion`.
+    ///
+    /// The area is in buffer coordinates and widened to whole bytes. `buffer` is a whole
+    /// frame, a shorter one is an `OutOfBoundsError`.
+    pub fn update_bw_region(&mut self, buffer: &[u8], area: Rectangle) -> Result<(), DisplayError> {
+        self.history.invalidate(FrameHistory::BW);
+        self.update_region(Cmd::WRITE_BWRAM, buffer, area)
+    }
+
+    /// Update part of the Red buffer, see `update_bw_region`
+    pub fn update_red_region(
+        &mut self,
+        buffer: &[u8],
+        area: Rectangle,
+    ) -> Result<(), DisplayError> {
+        self.history.invalidate(FrameHistory::RED);
+        self.red_cleared = false;
+        self.update_region(Cmd::WRITE_REDRAM, buffer, area)
+    }
+
+    /// Update the whole buffer of a black and white panel, e.g. from a `MonoDisplay`.
+    ///
+    /// The red RAM is bypassed instead of cleared, so it is never written. A bypassed
+    /// red RAM reads as 0, which panels with `red_active_low` show as red, so for these
+    /// it is cleared once instead.
+    pub fn update_mono_frame(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
+        if self.config.polarity.red_active_low {
+            if self.config.display_control.red == RamOption::Bypass {
+                self.set_display_control(DisplayControl {
+                    red: RamOption::Normal,
+                    ..self.config.display_control
+                })?;
+            }
+            if !self.red_cleared {
+                self.clear_red_frame()?;
+            }
+        } else if self.config.display_control.red != RamOption::Bypass {
+            self.set_display_control(DisplayControl {
+                red: RamOption::Bypass,
+                ..self.config.display_control
+            })?;
+        }
+        self.update_bw_frame(buffer)
     }
 
     /// Start an update of the whole display
     pub fn display_frame(&mut self, delay: &mut impl DelayNs) -> Result<(), DisplayError> {
-        self.interface
-            .cmd_with_data(Cmd::DISP_CTRL2, &[Flag::DISPLAY_MODE_1])?;
+        self.refresh(Flag::DISPLAY_MODE_1, delay)
+    }
+
+    /// Write a waveform LUT, used by refreshes that don't load the LUT from OTP
+    pub fn write_lut(&mut self, lut: &[u8; LUT_SIZE]) -> Result<(), DisplayError> {
+        self.history.changed = true;
+        self.interface.cmd_with_data(Cmd::WRITE_LUT, lut)
+    }
+
+    /// Show the bitplanes of a `GrayDisplay` in four gray levels.
+    ///
+    /// Send `GrayDisplay::bw_data` with `update_bw_frame` and `GrayDisplay::red_data`
+    /// with `update_red_frame` first. The planes are read as they are for the refresh, and
+    /// the display control set before is restored afterwards. The next `display_frame`
+    /// loads the OTP LUT again.
+    pub fn display_gray_frame(&mut self, delay: &mut impl DelayNs) -> Result<(), DisplayError> {
+        let control = self.config.display_control;
+        if control != DisplayControl::default() {
+            self.set_display_control(DisplayControl::default())?;
+        }
+        let result = self
+            .write_lut(&GRAY4)
+            .and_then(|_| self.refresh(Flag::DISPLAY_MODE_CUSTOM_LUT, delay));
+        if control != DisplayControl::default() {
+            self.set_display_control(control)?;
+        }
+        result
+    }
+
+    fn refresh(&mut self, mode: u8, delay: &mut impl DelayNs) -> Result<(), DisplayError> {
+        if !self.frame_changed() {
+            return Ok(());
+        }
+        self.interface.cmd_with_data(Cmd::DISP_CTRL2, &[mode])?;
         self.interface.cmd(Cmd::MASTER_ACTIVATE)?;
 
         self.interface.wait_until_idle(delay);
+        self.history.changed = false;
 
         Ok(())
     }
 
     /// Make the whole black and white frame on the display driver white
     pub fn clear_bw_frame(&mut self) -> Result<(), DisplayError> {
+        self.history.invalidate(FrameHistory::BW);
         self.use_full_frame()?;
 
-        let color = White.into();
+        let color = self.config.polarity.encode(TriColor::White).bw_byte();
 
         self.interface.cmd(Cmd::WRITE_BWRAM)?;
         self.interface
@@ -101,21 +356,136 @@ where
 
     /// Make the whole red frame on the display driver white
     pub fn clear_red_frame(&mut self) -> Result<(), DisplayError> {
+        self.history.invalidate(FrameHistory::RED);
+        self.red_cleared = false;
         self.use_full_frame()?;
 
-        let color = Red.into();
+        let color = self.config.polarity.encode(TriColor::White).red_byte();
 
         self.interface.cmd(Cmd::WRITE_REDRAM)?;
         self.interface
             .data_x_times(color, u32::from(WIDTH) / 8 * u32::from(HEIGHT))?;
+        self.red_cleared = true;
+        Ok(())
+    }
+
+    fn apply_config(&mut self) -> Result<(), DisplayError> {
+        if let Some(gate_voltage) = self.config.gate_voltage {
+            self.interface
+                .cmd_with_data(Cmd::GATE_VOLTAGE, &[gate_voltage.value()])?;
+        }
+        if let Some(source_voltage) = self.config.source_voltage {
+            self.interface
+                .cmd_with_data(Cmd::SOURCE_VOLTAGE, &source_voltage.data())?;
+        }
+        if let Some(booster) = self.config.booster {
+            self.interface
+                .cmd_with_data(Cmd::BOOSTER_SOFT_START, &booster.data())?;
+        }
+        if self.config.display_control != DisplayControl::default() {
+            self.interface
+                .cmd_with_data(Cmd::DISP_CTRL1, &self.config.display_control.data())?;
+        }
+        Ok(())
+    }
+
+    fn update_frame(
+        &mut self,
+        plane: usize,
+        command: u8,
+        buffer: &[u8],
+    ) -> Result<(), DisplayError> {
+        #[cfg(feature = "frame-diff")]
+        let rows = if self.history.enabled {
+            if buffer.len() != BUFFER_SIZE {
+                return Err(DisplayError::OutOfBoundsError);
+            }
+            match self.history.update(plane, buffer) {
+                Some(rows) => rows,
+                None => return Ok(()),
+            }
+        } else {
+            self.history.changed = true;
+            (0, u32::from(HEIGHT) - 1)
+        };
+        #[cfg(not(feature = "frame-diff"))]
+        let rows = {
+            self.history.changed = true;
+            (0, u32::from(HEIGHT) - 1)
+        };
+
+        let result = if rows == (0, u32::from(HEIGHT) - 1) || self.config.data_entry_mode.y_first()
+        {
+            self.use_full_frame()
+                .and_then(|_| self.interface.cmd_with_data(command, buffer))
+        } else {
+            let (first, last) = rows;
+            let band = Rectangle::new(
+                Point::new(0, first as i32),
+                Size::new(WIDTH.into(), last - first + 1),
+            );
+            self.update_region(command, buffer, band)
+        };
+        if result.is_err() {
+            self.history.invalidate(plane);
+        }
+        result
+    }
+
+    fn update_region(
+        &mut self,
+        command: u8,
+        buffer: &[u8],
+        area: Rectangle,
+    ) -> Result<(), DisplayError> {
+        if buffer.len() < BYTES_PER_ROW * HEIGHT as usize {
+            return Err(DisplayError::OutOfBoundsError);
+        }
+        let area = visible(&area, Size::new(WIDTH.into(), HEIGHT.into()));
+        let Some(bottom_right) = area.bottom_right() else {
+            return Ok(());
+        };
+        let mode = self.config.data_entry_mode;
+        // rows of the buffer are only contiguous in RAM when X is written first
+        if mode.y_first() {
+            return Err(DisplayError::DataFormatNotImplemented);
+        }
+
+        let (first, last) = (area.top_left.x as u32 / 8, bottom_right.x as u32 / 8);
+        let (first_row, last_row) = (area.top_left.y as u32, bottom_right.y as u32);
+
+        // the controller mirrors the buffer where an address counts down
+        let (max_x, max_y) = (u32::from(WIDTH) - 1, u32::from(HEIGHT) - 1);
+        let (start_x, end_x) = if mode.x_increments() {
+            (first * 8, last * 8 + 7)
+        } else {
+            (max_x - (last * 8 + 7), max_x - first * 8)
+        };
+        let (start_y, end_y) = if mode.y_increments() {
+            (first_row, last_row)
+        } else {
+            (max_y - last_row, max_y - first_row)
+        };
+        self.set_ram_area(start_x, start_y, end_x, end_y)?;
+        self.set_ram_origin(start_x, start_y, end_x, end_y)?;
+
+        self.interface.cmd(command)?;
+        for row in buffer
+            .chunks_exact(BYTES_PER_ROW)
+            .skip(first_row as usize)
+            .take((last_row - first_row + 1) as usize)
+        {
+            self.interface.data(&row[first as usize..=last as usize])?;
+        }
         Ok(())
     }
 
     fn use_full_frame(&mut self) -> Result<(), DisplayError> {
+        let (end_x, end_y) = (u32::from(WIDTH) - 1, u32::from(HEIGHT) - 1);
         // choose full frame/ram
-        self.set_ram_area(0, 0, u32::from(WIDTH) - 1, u32::from(HEIGHT) - 1)?;
+        self.set_ram_area(0, 0, end_x, end_y)?;
         // start from the beginning
-        self.set_ram_counter(0, 0)
+        self.set_ram_origin(0, 0, end_x, end_y)
     }
 
     fn set_ram_area(
@@ -125,8 +495,22 @@ where
         end_x: u32,
         end_y: u32,
     ) -> Result<(), DisplayError> {
-        assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        assert!(start_x <= end_x);
+        assert!(start_y <= end_y);
+
+        // the window is walked from its start to its end address, so a decrementing
+        // counter needs them swapped
+        let mode = self.config.data_entry_mode;
+        let (start_x, end_x) = if mode.x_increments() {
+            (start_x, end_x)
+        } else {
+            (end_x, start_x)
+        };
+        let (start_y, end_y) = if mode.y_increments() {
+            (start_y, end_y)
+        } else {
+            (end_y, start_y)
+        };
 
         self.interface.cmd_with_data(
             Cmd::SET_RAMXPOS,
@@ -144,6 +528,20 @@ where
         Ok(())
     }
 
+    /// Point the RAM counter at the corner of the window where the data entry mode starts
+    fn set_ram_origin(
+        &mut self,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), DisplayError> {
+        let mode = self.config.data_entry_mode;
+        let x = if mode.x_increments() { start_x } else { end_x };
+        let y = if mode.y_increments() { start_y } else { end_y };
+        self.set_ram_counter(x, y)
+    }
+
     fn set_ram_counter(&mut self, x: u32, y: u32) -> Result<(), DisplayError> {
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -155,3 +553,319 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::testing::{self, Driver};
+    use crate::energy::Model;
+    use crate::graphics::BUFFER_SIZE;
+    use crate::mock::{BusyTime, BusyTiming, Event, Mock};
+
+    /// Initialised driver with the log of its initialisation cleared
+    fn start(mock: &Mock) -> Driver {
+        let driver = testing::start(mock.interface(), &mut mock.delay());
+        mock.clear();
+        driver
+    }
+
+    const FULL_WINDOW: [(u8, &[u8]); 4] = [
+        (Cmd::SET_RAMXPOS, &[0, 24]),
+        (Cmd::SET_RAMYPOS, &[0, 0, 199, 0]),
+        (Cmd::SET_RAMXCOUNT, &[0]),
+        (Cmd::SET_RAMYCOUNT, &[0, 0]),
+    ];
+
+    #[test]
+    fn init_sequence() {
+        let mock = Mock::new();
+        let (spi, cs, busy, dc, rst) = mock.interface();
+        Ssd1681::new(spi, cs, busy, dc, rst, &mut mock.delay()).unwrap();
+
+        assert_eq!(
+            [
+                Event::Rst(false),
+                Event::Delay(10_000_000),
+                Event::Rst(true),
+                Event::Delay(10_000_000),
+                Event::Cs(true),
+                Event::Dc(false),
+                Event::Cs(false),
+                Event::Command(Cmd::SW_RESET),
+            ],
+            mock.events()[..8]
+        );
+        mock.assert_transactions(&[
+            (Cmd::SW_RESET, &[]),
+            (Cmd::DRIVER_CONTROL, &[199, 0, 0]),
+            (Cmd::DATA_MODE, &[Flag::DATA_ENTRY_INCRY_INCRX]),
+            FULL_WINDOW[0],
+            FULL_WINDOW[1],
+            FULL_WINDOW[2],
+            FULL_WINDOW[3],
+            (Cmd::WRITE_BORDER, &[0x05]),
+            (Cmd::TEMP_CONTROL, &[Flag::INTERNAL_TEMP_SENSOR]),
+        ]);
+    }
+
+    #[test]
+    fn update_bw_frame_sends_full_window_and_buffer() {
+        let mock = Mock::new();
+        let mut driver = start(&mock);
+        let mut frame = [0xFF; BYTES_PER_ROW * HEIGHT as usize];
+        frame[7] = 0x0F;
+        driver.update_bw_frame(&frame).unwrap();
+
+        let mut expected = FULL_WINDOW.to_vec();
+        expected.push((Cmd::WRITE_BWRAM, &frame));
+        mock.assert_transactions(&expected);
+    }
+
+    #[test]
+    fn clear_frames_fill_ram() {
+        let mock = Mock::new();
+        let mut driver = start(&mock);
+        driver.clear_bw_frame().unwrap();
+        driver.clear_red_frame().unwrap();
+
+        let white = [0xFF; BYTES_PER_ROW * HEIGHT as usize];
+        let no_red = [0x00; BYTES_PER_ROW * HEIGHT as usize];
+        let mut expected = FULL_WINDOW.to_vec();
+        expected.push((Cmd::WRITE_BWRAM, &white));
+        expected.extend(FULL_WINDOW);
+        expected.push((Cmd::WRITE_REDRAM, &no_red));
+        mock.assert_transactions(&expected);
+    }
+
+    #[test]
+    fn display_frame_activates_update() {
+        let mock = Mock::new();
+        let mut driver = start(&mock);
+        driver.display_frame(&mut mock.delay()).unwrap();
+
+        mock.assert_events(&[
+            Event::Cs(true),
+            Event::Dc(false),
+            Event::Cs(false),
+            Event::Command(Cmd::DISP_CTRL2),
+            Event::Dc(true),
+            Event::Data(Flag::DISPLAY_MODE_1),
+            Event::Cs(true),
+            Event::Dc(false),
+            Event::Cs(false),
+            Event::Command(Cmd::MASTER_ACTIVATE),
+        ]);
+    }
+
+    #[test]
+    #[cfg(feature = "frame-diff")]
+    fn frame_history_finds_changed_rows() {
+        let mut history = FrameHistory::new();
+        let mut frame = [0xFF; BYTES_PER_ROW * HEIGHT as usize];
+        assert_eq!(Some((0, 199)), history.update(FrameHistory::BW, &frame));
+        assert_eq!(None, history.update(FrameHistory::BW, &frame));
+
+        frame[BYTES_PER_ROW * 20 + 3] = 0x00;
+        frame[BYTES_PER_ROW * 41] = 0x0F;
+        assert_eq!(Some((20, 41)), history.update(FrameHistory::BW, &frame));
+        assert_eq!(Some((0, 199)), history.update(FrameHistory::RED, &frame));
+
+        history.invalidate(FrameHistory::BW);
+        assert_eq!(Some((0, 199)), history.update(FrameHistory::BW, &frame));
+    }
+
+    /// Driver diffing frames, with a frame sent and shown
+    #[cfg(feature = "frame-diff")]
+    fn start_diffing(mock: &Mock, frame: &[u8; BUFFER_SIZE]) -> Driver {
+        let mut driver = start(mock);
+        driver.set_frame_diff(true);
+        driver.update_bw_frame(frame).unwrap();
+        driver.display_frame(&mut mock.delay()).unwrap();
+        assert!(!driver.frame_changed());
+        driver
+    }
+
+    #[test]
+    #[cfg(feature = "frame-diff")]
+    fn frame_diff_skips_unchanged_refresh() {
+        let mock = Mock::new();
+        let frame = [0xF0; BUFFER_SIZE];
+        let mut driver = start_diffing(&mock, &frame);
+        mock.clear();
+        driver.update_bw_frame(&frame).unwrap();
+        driver.display_frame(&mut mock.delay()).unwrap();
+        assert_eq!(&[] as &[Event], &mock.events()[..]);
+    }
+
+    #[test]
+    #[cfg(feature = "frame-diff")]
+    fn frame_diff_refreshes_after_night_mode() {
+        let mock = Mock::new();
+        let mut driver = start_diffing(&mock, &[0xF0; BUFFER_SIZE]);
+        driver
+            .set_display_control(DisplayControl {
+                bw: RamOption::Inverse,
+                ..DisplayControl::default()
+            })
+            .unwrap();
+        driver.display_frame(&mut mock.delay()).unwrap();
+        assert_eq!(2, mock.refreshes().len());
+    }
+
+    #[test]
+    #[cfg(feature = "frame-diff")]
+    fn frame_diff_refreshes_after_border_change() {
+        let mock = Mock::new();
+        let mut driver = start_diffing(&mock, &[0xF0; BUFFER_SIZE]);
+        driver.set_border(BorderWaveform::FixVsh1).unwrap();
+        driver.display_frame(&mut mock.delay()).unwrap();
+        assert_eq!(2, mock.refreshes().len());
+    }
+
+    #[test]
+    #[cfg(feature = "frame-diff")]
+    fn frame_diff_resends_unchanged_frame_after_rotation() {
+        let mock = Mock::new();
+        let frame = [0xF0; BUFFER_SIZE];
+        let mut driver = start_diffing(&mock, &frame);
+        driver.set_rotation(DisplayRotation::Rotate90).unwrap();
+        mock.clear();
+        driver.update_bw_frame(&frame).unwrap();
+        driver.display_frame(&mut mock.delay()).unwrap();
+
+        let operations = mock.operations();
+        assert!(operations
+            .iter()
+            .any(|op| op.command == Some(Cmd::WRITE_BWRAM)));
+        assert_eq!(1, mock.refreshes().len());
+    }
+
+    #[test]
+    #[cfg(feature = "frame-diff")]
+    fn frame_diff_rejects_frames_of_other_sizes() {
+        let mock = Mock::new();
+        let mut driver = start(&mock);
+        driver.set_frame_diff(true);
+        let long = [0xFF; BUFFER_SIZE + BYTES_PER_ROW];
+        assert!(matches!(
+            driver.update_bw_frame(&long),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+        assert!(matches!(
+            driver.update_red_frame(&long[..BYTES_PER_ROW]),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+        assert_eq!(&[] as &[Event], &mock.events()[..]);
+    }
+
+    #[test]
+    fn region_update_checks_the_buffer_and_clips_the_area() {
+        let mock = Mock::new();
+        let mut driver = start(&mock);
+        let frame = [0xFF; BUFFER_SIZE];
+        mock.clear();
+        let area = Rectangle::new(Point::new(0, 10), Size::new(8, 2));
+        assert!(matches!(
+            driver.update_bw_region(&frame[..BUFFER_SIZE - 1], area),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+        assert_eq!(&[] as &[Event], &mock.events()[..]);
+
+        let huge = Rectangle::new(Point::new(192, 199), Size::new(u32::MAX, u32::MAX));
+        driver.update_red_region(&frame, huge).unwrap();
+        let operations = mock.operations();
+        let write = operations.last().unwrap();
+        assert_eq!(Some(Cmd::WRITE_REDRAM), write.command);
+        assert_eq!(1, write.len());
+    }
+
+    #[test]
+    fn gray_refresh_restores_display_control() {
+        let mock = Mock::new();
+        let mut driver = start(&mock);
+        let night = DisplayControl {
+            bw: RamOption::Inverse,
+            ..DisplayControl::default()
+        };
+        driver.set_display_control(night).unwrap();
+        mock.clear();
+        driver.display_gray_frame(&mut mock.delay()).unwrap();
+
+        mock.assert_transactions(&[
+            (Cmd::DISP_CTRL1, &DisplayControl::default().data()),
+            (Cmd::WRITE_LUT, &GRAY4),
+            (Cmd::DISP_CTRL2, &[Flag::DISPLAY_MODE_CUSTOM_LUT]),
+            (Cmd::MASTER_ACTIVATE, &[]),
+            (Cmd::DISP_CTRL1, &night.data()),
+        ]);
+        assert_eq!(night, driver.config.display_control);
+    }
+
+    #[test]
+    #[cfg(feature = "frame-diff")]
+    fn frame_diff_keeps_gray_refresh() {
+        let mock = Mock::new();
+        let frame = [0xF0; BUFFER_SIZE];
+        let mut driver = start_diffing(&mock, &frame);
+        driver.update_bw_frame(&frame).unwrap();
+        driver.display_gray_frame(&mut mock.delay()).unwrap();
+        assert_eq!(2, mock.refreshes().len());
+    }
+
+    #[test]
+    fn init_waits_for_reset() {
+        let mock = Mock::with_busy_timing(BusyTiming {
+            reset: BusyTime::Millis(20),
+            sw_reset: BusyTime::Polls(3),
+            ..BusyTiming::default()
+        });
+        let (spi, cs, busy, dc, rst) = mock.interface();
+        Ssd1681::new(spi, cs, busy, dc, rst, &mut mock.delay()).unwrap();
+
+        // 10 ms of the reset period pass in the delay after RST goes high
+        assert_eq!(10, mock.busy_polls());
+        assert_eq!(10_000_000, mock.busy_wait_ns());
+    }
+
+    #[test]
+    fn display_frame_waits_for_refresh() {
+        let mock = Mock::with_busy_timing(BusyTiming {
+            activate: BusyTime::Millis(1500),
+            ..BusyTiming::default()
+        });
+        let mut driver = start(&mock);
+        driver.display_frame(&mut mock.delay()).unwrap();
+        assert_eq!(1500, mock.busy_polls());
+        assert_eq!(1_500_000_000, mock.busy_wait_ns());
+
+        let mock = Mock::with_busy_timing(BusyTiming {
+            activate: BusyTime::Polls(4),
+            ..BusyTiming::default()
+        });
+        let mut driver = start(&mock);
+        driver.display_frame(&mut mock.delay()).unwrap();
+        assert_eq!(4, mock.busy_polls());
+        assert_eq!(4_000_000, mock.busy_wait_ns());
+    }
+
+    #[test]
+    fn refreshes_are_estimated() {
+        let mock = Mock::new();
+        let mut driver = start(&mock);
+        let model = Model::default();
+        driver.display_frame(&mut mock.delay()).unwrap();
+        let full = model.estimate(Flag::DISPLAY_MODE_1, None, 25);
+        assert_eq!([full], &mock.refreshes()[..]);
+
+        mock.set_temperature(0);
+        driver.update_bw_frame(&[0xFF; BUFFER_SIZE]).unwrap();
+        driver.display_gray_frame(&mut mock.delay()).unwrap();
+        driver.update_bw_frame(&[0x00; BUFFER_SIZE]).unwrap();
+        driver.display_frame(&mut mock.delay()).unwrap();
+        let gray = model.estimate(Flag::DISPLAY_MODE_CUSTOM_LUT, Some(&GRAY4), 0);
+        let cold = model.estimate(Flag::DISPLAY_MODE_1, None, 0);
+        assert_eq!([full, gray, cold], &mock.refreshes()[..]);
+        assert_eq!(full + gray + cold, mock.refresh_total());
+        assert!(cold.duration_us > full.duration_us);
+    }
+}
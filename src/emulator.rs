@@ -0,0 +1,508 @@
+//! Headless software model of the SSD1681 for host-side tests
+//!
+//! [`Emulator`] hands out the [`bus`] SPI device and pins, and decodes the command stream
+//! the driver sends over them: RAM windows and address counters, the data entry mode, BW
+//! and red RAM writes, display update control and `MASTER_ACTIVATE`. Each display update
+//! copies the RAM onto an emulated panel, which can be inspected pixel by pixel.
+//!
+//! The panel shows the colors the RAM bits select with the built-in waveforms. Custom LUTs
+//! are recorded but not simulated, and the gate scan settings other than the top/bottom
+//! flip are ignored. BUSY always reads idle.
+//!
+//! ```
+//! use epd_ssd1681::color::TriColor;
+//! use epd_ssd1681::driver::Ssd1681;
+//! use epd_ssd1681::emulator::Emulator;
+//!
+//! let emulator = Emulator::new();
+//! let (spi, cs, busy, dc, rst) = emulator.interface();
+//! let mut delay = emulator.delay();
+//! let mut ssd1681 = Ssd1681::new(spi, cs, busy, dc, rst, &mut delay).unwrap();
+//! ssd1681.clear_bw_frame().unwrap();
+//! ssd1681.clear_red_frame().unwrap();
+//! ssd1681.display_frame(&mut delay).unwrap();
+//! assert_eq!(TriColor::White, emulator.pixel(10, 10));
+//! ```
+
+use crate::bus::{self, Bus, Busy, Delay, Device, Line, Pin, Spi};
+use crate::cmd::Cmd;
+use crate::color::{PlaneBits, Polarity, TriColor};
+use crate::energy::{self, Estimate};
+use crate::flag::Flag;
+use crate::lut::LUT_SIZE;
+use crate::{HEIGHT, WIDTH};
+use std::cell::{Ref, RefCell};
+use std::rc::Rc;
+use std::vec;
+use std::vec::Vec;
+
+/// Number of X addresses, one per 8 pixels of a RAM row
+const RAM_COLUMNS: u16 = WIDTH as u16 / 8;
+const RAM_ROWS: u16 = HEIGHT as u16;
+
+/// DISP_CTRL2 bit that shows the RAM on the panel
+const DISPLAY_PATTERN: u8 = 0x04;
+/// DISP_CTRL2 bit that loads the LUT from OTP, replacing a written one
+const LOAD_LUT: u8 = 0x10;
+
+/// Room temperature the internal sensor reads unless set otherwise
+const TEMPERATURE: i8 = 25;
+
+/// Register values the controller starts with after a reset
+#[derive(Clone, Copy, Debug)]
+struct Registers {
+    data_entry_mode: u8,
+    gate_scan: u8,
+    /// RAM X window in bytes, start and end
+    x_window: (u16, u16),
+    /// RAM Y window in rows, start and end
+    y_window: (u16, u16),
+    x_counter: u16,
+    y_counter: u16,
+    display_control: [u8; 2],
+    update_control: u8,
+    border: u8,
+}
+
+impl Default for Registers {
+    fn default() -> Self {
+        Registers {
+            data_entry_mode: Flag::DATA_ENTRY_INCRY_INCRX,
+            gate_scan: 0,
+            x_window: (0, RAM_COLUMNS - 1),
+            y_window: (0, RAM_ROWS - 1),
+            x_counter: 0,
+            y_counter: 0,
+            display_control: [0, 0],
+            update_control: 0xFF,
+            border: 0xC0,
+        }
+    }
+}
+
+/// Controller state the bus handles write to
+#[derive(Debug)]
+pub(crate) struct Controller {
+    registers: Registers,
+    polarity: Polarity,
+    in_reset: bool,
+    command: Option<u8>,
+    params: Vec<u8>,
+    bw_ram: Vec<u8>,
+    red_ram: Vec<u8>,
+    lut: Option<Vec<u8>>,
+    /// Temperature register, in °C
+    temperature: i8,
+    panel: Vec<TriColor>,
+    refreshes: usize,
+    elapsed_ns: u64,
+}
+
+impl Controller {
+    pub(crate) fn new(polarity: Polarity) -> Self {
+        let ram_size = RAM_COLUMNS as usize * RAM_ROWS as usize;
+        Controller {
+            registers: Registers::default(),
+            polarity,
+            in_reset: false,
+            command: None,
+            params: Vec::new(),
+            bw_ram: vec![0; ram_size],
+            red_ram: vec![0; ram_size],
+            lut: None,
+            temperature: TEMPERATURE,
+            panel: vec![TriColor::White; WIDTH as usize * HEIGHT as usize],
+            refreshes: 0,
+            elapsed_ns: 0,
+        }
+    }
+
+    /// Reset the registers, the RAM keeps its contents
+    fn reset(&mut self) {
+        self.registers = Registers::default();
+        self.command = None;
+        self.params.clear();
+        self.lut = None;
+    }
+
+    fn cmd(&mut self, command: u8) {
+        self.command = Some(command);
+        self.params.clear();
+        match command {
+            Cmd::SW_RESET => self.reset(),
+            Cmd::MASTER_ACTIVATE => self.activate(),
+            Cmd::WRITE_LUT => self.lut = Some(Vec::new()),
+            _ => {}
+        }
+    }
+
+    fn write_data(&mut self, byte: u8) {
+        let Some(command) = self.command else {
+            return;
+        };
+        let regs = &mut self.registers;
+        let index = self.params.len();
+        self.params.push(byte);
+        let params = &self.params;
+        match (command, index) {
+            (Cmd::WRITE_BWRAM, _) => self.write_ram(byte, false),
+            (Cmd::WRITE_REDRAM, _) => self.write_ram(byte, true),
+            (Cmd::WRITE_LUT, _) => {
+                if let Some(lut) = self.lut.as_mut() {
+                    lut.push(byte);
+                }
+            }
+            (Cmd::DRIVER_CONTROL, 2) => regs.gate_scan = byte,
+            (Cmd::DATA_MODE, 0) => regs.data_entry_mode = byte & 0x07,
+            (Cmd::SET_RAMXPOS, 1) => {
+                regs.x_window = (u16::from(params[0] & 0x3F), u16::from(params[1] & 0x3F))
+            }
+            (Cmd::SET_RAMYPOS, 3) => {
+                regs.y_window = (
+                    u16::from_le_bytes([params[0], params[1] & 0x01]),
+                    u16::from_le_bytes([params[2], params[3] & 0x01]),
+                )
+            }
+            (Cmd::SET_RAMXCOUNT, 0) => regs.x_counter = u16::from(byte & 0x3F),
+            (Cmd::SET_RAMYCOUNT, 1) => {
+                regs.y_counter = u16::from_le_bytes([params[0], params[1] & 0x01])
+            }
+            (Cmd::DISP_CTRL1, 0..=1) => regs.display_control[index] = byte,
+            (Cmd::DISP_CTRL2, 0) => regs.update_control = byte,
+            (Cmd::WRITE_BORDER, 0) => regs.border = byte,
+            (Cmd::TEMP_WRITE, 0) => self.temperature = byte as i8,
+            _ => {}
+        }
+    }
+
+    /// Store a byte at the address counter and advance it in the data entry mode
+    fn write_ram(&mut self, byte: u8, red: bool) {
+        let regs = &mut self.registers;
+        let (x, y) = (regs.x_counter, regs.y_counter);
+        if x < RAM_COLUMNS && y < RAM_ROWS {
+            let ram = if red {
+                &mut self.red_ram
+            } else {
+                &mut self.bw_ram
+            };
+            ram[y as usize * RAM_COLUMNS as usize + x as usize] = byte;
+        }
+
+        let mode = regs.data_entry_mode;
+        let x_step = if mode & 0x01 != 0 { 1 } else { -1 };
+        let y_step = if mode & 0x02 != 0 { 1 } else { -1 };
+        if mode & Flag::DATA_ENTRY_Y_FIRST != 0 {
+            if step(&mut regs.y_counter, y_step, regs.y_window, RAM_ROWS) {
+                step(&mut regs.x_counter, x_step, regs.x_window, RAM_COLUMNS);
+            }
+        } else if step(&mut regs.x_counter, x_step, regs.x_window, RAM_COLUMNS) {
+            step(&mut regs.y_counter, y_step, regs.y_window, RAM_ROWS);
+        }
+    }
+
+    /// Run the display update sequence selected with DISP_CTRL2
+    fn activate(&mut self) {
+        if self.registers.update_control & LOAD_LUT != 0 {
+            self.lut = None;
+        }
+        if self.registers.update_control & DISPLAY_PATTERN == 0 {
+            return;
+        }
+        let [control, _] = self.registers.display_control;
+        let flip = self.registers.gate_scan & Flag::DRIVER_CONTROL_TB != 0;
+        for y in 0..HEIGHT as usize {
+            let row = if flip { HEIGHT as usize - 1 - y } else { y };
+            for x in 0..WIDTH as usize {
+                let index = row * RAM_COLUMNS as usize + x / 8;
+                let bit = 0x80 >> (x % 8);
+                let bits = PlaneBits {
+                    bw: ram_bit(self.bw_ram[index] & bit != 0, control & 0x0F),
+                    red: ram_bit(self.red_ram[index] & bit != 0, control >> 4),
+                };
+                self.panel[y * WIDTH as usize + x] = self.polarity.decode(bits);
+            }
+        }
+        self.refreshes += 1;
+    }
+
+    /// Estimate of a `MASTER_ACTIVATE` sent now, with the sequence, LUT and temperature
+    /// in the registers
+    pub(crate) fn estimate(&self, model: &energy::Model) -> Estimate {
+        let lut = self
+            .lut
+            .as_deref()
+            .and_then(|lut| <&[u8; LUT_SIZE]>::try_from(lut).ok());
+        model.estimate(self.registers.update_control, lut, self.temperature)
+    }
+
+    /// Set the temperature the internal sensor reads, in °C
+    pub(crate) fn set_temperature(&mut self, celsius: i8) {
+        self.temperature = celsius;
+    }
+}
+
+impl Device for Controller {
+    fn command(&mut self, command: u8) {
+        if !self.in_reset {
+            self.cmd(command);
+        }
+    }
+
+    fn data(&mut self, byte: u8) {
+        if !self.in_reset {
+            self.write_data(byte);
+        }
+    }
+
+    fn pin(&mut self, line: Line, high: bool) {
+        if line == Line::Rst {
+            if !high {
+                self.reset();
+            }
+            self.in_reset = !high;
+        }
+    }
+
+    fn delay(&mut self, ns: u32) {
+        self.elapsed_ns += u64::from(ns);
+    }
+}
+
+/// Move an address counter one step through its window, returns whether it wrapped
+fn step(counter: &mut u16, step: i32, window: (u16, u16), size: u16) -> bool {
+    if *counter == window.1 {
+        *counter = window.0;
+        true
+    } else {
+        *counter = (i32::from(*counter) + step).rem_euclid(i32::from(size)) as u16;
+        false
+    }
+}
+
+/// Apply a DISP_CTRL1 RAM option nibble to a RAM bit
+fn ram_bit(bit: bool, option: u8) -> bool {
+    match option & 0x0C {
+        0x04 => false,
+        0x08 => !bit,
+        _ => bit,
+    }
+}
+
+/// Emulated SSD1681 with a 200x200 panel
+///
+/// Cloning the emulator, or any of its handles, shares the same controller.
+#[derive(Clone, Debug)]
+pub struct Emulator {
+    bus: Rc<RefCell<Bus<Controller>>>,
+}
+
+impl Default for Emulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Emulator {
+    /// Emulator for a panel with the default polarity
+    pub fn new() -> Self {
+        Self::with_polarity(Polarity::default())
+    }
+
+    /// Emulator for a panel that reads its RAM with `polarity`
+    pub fn with_polarity(polarity: Polarity) -> Self {
+        Emulator {
+            bus: Bus::shared(Controller::new(polarity)),
+        }
+    }
+
+    /// SPI device, CS, BUSY, DC and RST in the order `Ssd1681::new` takes them
+    pub fn interface(&self) -> (Spi, Pin, Busy, Pin, Pin) {
+        bus::interface(&self.bus)
+    }
+
+    /// Delay that adds to the emulated time instead of sleeping
+    pub fn delay(&self) -> Delay {
+        bus::delay(&self.bus)
+    }
+
+    fn state(&self) -> Ref<'_, Controller> {
+        Ref::map(self.bus.borrow(), |bus| &bus.device)
+    }
+
+    /// Color of a panel pixel as of the last display update
+    pub fn pixel(&self, x: u32, y: u32) -> TriColor {
+        assert!(x < WIDTH as u32 && y < HEIGHT as u32);
+        self.state().panel[(y * WIDTH as u32 + x) as usize]
+    }
+
+    /// All panel pixels as of the last display update, row by row
+    pub fn image(&self) -> Vec<TriColor> {
+        self.state().panel.clone()
+    }
+
+    /// BW RAM contents, 25 bytes per row
+    pub fn bw_ram(&self) -> Vec<u8> {
+        self.state().bw_ram.clone()
+    }
+
+    /// Red RAM contents, 25 bytes per row
+    pub fn red_ram(&self) -> Vec<u8> {
+        self.state().red_ram.clone()
+    }
+
+    /// Last LUT written with `Cmd::WRITE_LUT` since the last reset or OTP LUT load
+    pub fn lut(&self) -> Option<Vec<u8>> {
+        self.state().lut.clone()
+    }
+
+    /// Current data entry mode register
+    pub fn data_entry_mode(&self) -> u8 {
+        self.state().registers.data_entry_mode
+    }
+
+    /// Current border waveform register
+    pub fn border(&self) -> u8 {
+        self.state().registers.border
+    }
+
+    /// Number of display updates that showed the RAM on the panel
+    pub fn refresh_count(&self) -> usize {
+        self.state().refreshes
+    }
+
+    /// Time spent in [`Delay`] and SPI delay operations
+    pub fn elapsed_ns(&self) -> u64 {
+        self.state().elapsed_ns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::testing::{draw_pattern, show, start, ROTATIONS};
+    use crate::config::PanelConfig;
+    use crate::driver::Ssd1681;
+    use crate::graphics::{Display, MonoDisplay};
+    use embedded_graphics::pixelcolor::BinaryColor;
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::Rectangle;
+
+    #[test]
+    fn frame_ends_up_on_the_panel() {
+        let emulator = Emulator::new();
+        let mut driver = start(emulator.interface(), &mut emulator.delay());
+        let mut display = Display::new();
+        draw_pattern(&mut display);
+        show(&mut driver, &display, &mut emulator.delay());
+
+        assert_eq!(1, emulator.refresh_count());
+        assert_eq!(&display.black_data()[..], &emulator.bw_ram()[..]);
+        assert_eq!(&display.red_data()[..], &emulator.red_ram()[..]);
+        assert_eq!(TriColor::Black, emulator.pixel(10, 20));
+        assert_eq!(TriColor::Black, emulator.pixel(39, 59));
+        assert_eq!(TriColor::White, emulator.pixel(40, 59));
+        assert_eq!(TriColor::Red, emulator.pixel(199, 3));
+        assert_eq!(TriColor::White, emulator.pixel(0, 0));
+    }
+
+    #[test]
+    fn ram_is_shown_only_after_an_update() {
+        let emulator = Emulator::new();
+        let mut driver = start(emulator.interface(), &mut emulator.delay());
+        driver.clear_bw_frame().unwrap();
+        driver.clear_red_frame().unwrap();
+        driver.display_frame(&mut emulator.delay()).unwrap();
+
+        let mut display = Display::new();
+        display.clear(TriColor::Black);
+        driver.update_bw_frame(display.black_data()).unwrap();
+        assert_eq!(TriColor::White, emulator.pixel(100, 100));
+        driver.display_frame(&mut emulator.delay()).unwrap();
+        assert_eq!(TriColor::Black, emulator.pixel(100, 100));
+        assert_eq!(2, emulator.refresh_count());
+    }
+
+    #[test]
+    fn hardware_rotation_matches_software_rotation() {
+        for rotation in ROTATIONS {
+            let software = Emulator::new();
+            let mut driver = start(software.interface(), &mut software.delay());
+            let mut display = Display::new();
+            display.set_rotation(rotation);
+            draw_pattern(&mut display);
+            show(&mut driver, &display, &mut software.delay());
+
+            let hardware = Emulator::new();
+            let mut driver = start(hardware.interface(), &mut hardware.delay());
+            driver.set_rotation(rotation).unwrap();
+            let mut display = Display::new();
+            display.set_hardware_rotation(true);
+            display.set_rotation(rotation);
+            draw_pattern(&mut display);
+            show(&mut driver, &display, &mut hardware.delay());
+
+            assert!(software.image() == hardware.image(), "{:?}", rotation);
+        }
+    }
+
+    #[test]
+    fn mono_frame_shows_no_red_on_active_low_panels() {
+        let polarity = Polarity {
+            red_active_low: true,
+            ..Polarity::default()
+        };
+        let config = PanelConfig {
+            polarity,
+            ..PanelConfig::default()
+        };
+        let emulator = Emulator::with_polarity(polarity);
+        let (spi, cs, busy, dc, rst) = emulator.interface();
+        let mut delay = emulator.delay();
+        let mut driver = Ssd1681::with_config(spi, cs, busy, dc, rst, config, &mut delay).unwrap();
+
+        let mut display = MonoDisplay::with_config(driver.config());
+        display.clear(BinaryColor::Off);
+        let area = Rectangle::new(Point::new(8, 8), Size::new(16, 4));
+        display.fill_solid(&area, BinaryColor::On).unwrap();
+        for _ in 0..2 {
+            driver.update_mono_frame(display.data()).unwrap();
+            driver.display_frame(&mut emulator.delay()).unwrap();
+        }
+
+        for y in 0..HEIGHT as u32 {
+            for x in 0..WIDTH as u32 {
+                let expected = if area.contains(Point::new(x as i32, y as i32)) {
+                    TriColor::Black
+                } else {
+                    TriColor::White
+                };
+                assert_eq!(expected, emulator.pixel(x, y), "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn region_update_writes_only_the_window() {
+        let emulator = Emulator::new();
+        let mut driver = start(emulator.interface(), &mut emulator.delay());
+        driver.clear_bw_frame().unwrap();
+        driver.clear_red_frame().unwrap();
+
+        let mut display = Display::new();
+        display.clear(TriColor::Black);
+        let area = Rectangle::new(Point::new(16, 30), Size::new(24, 10));
+        driver.update_bw_region(display.black_data(), area).unwrap();
+        driver.display_frame(&mut emulator.delay()).unwrap();
+
+        for y in 0..HEIGHT as u32 {
+            for x in 0..WIDTH as u32 {
+                let expected = if area.contains(Point::new(x as i32, y as i32)) {
+                    TriColor::Black
+                } else {
+                    TriColor::White
+                };
+                assert_eq!(expected, emulator.pixel(x, y), "({}, {})", x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Refresh duration and charge estimates for battery sizing
+//!
+//! A [`Model`] walks the steps a `DISP_CTRL2` sequence enables, from turning the clock on
+//! to turning it off, and adds up how long each takes and the charge it draws. The
+//! waveform itself lasts as long as its LUT says when a LUT written with `WRITE_LUT` is
+//! used, or as long as the matching OTP waveform at the panel temperature otherwise.
+//!
+//! The default figures are typical values for a 1.54" tri-color panel, measure your own
+//! board and adjust the [`Model`] fields for anything better than a rough estimate.
+
+use crate::lut::LUT_SIZE;
+use core::fmt;
+use core::iter::Sum;
+use core::ops::{Add, AddAssign};
+
+/// DISP_CTRL2 steps, in the order the controller runs them
+const CLOCK_ON: u8 = 0x80;
+const ANALOG_ON: u8 = 0x40;
+const LOAD_TEMPERATURE: u8 = 0x20;
+const LOAD_LUT: u8 = 0x10;
+const MODE_2: u8 = 0x08;
+const DISPLAY: u8 = 0x04;
+const ANALOG_OFF: u8 = 0x02;
+const CLOCK_OFF: u8 = 0x01;
+
+/// Offset of the phase lengths and repeats of the 12 LUT groups
+const LUT_TIMING: usize = 60;
+/// Offset of the frame rate settings, one nibble per group
+const LUT_FRAME_RATE: usize = 144;
+const LUT_GROUPS: usize = 12;
+
+/// Estimated duration of a refresh and the charge it draws
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Estimate {
+    /// Duration in microseconds
+    pub duration_us: u64,
+    /// Charge in nanocoulombs
+    pub charge_nc: u64,
+}
+
+impl Estimate {
+    /// Charge in microampere hours, the unit of battery capacities
+    pub fn charge_uah(&self) -> f32 {
+        self.charge_nc as f32 / 3_600_000.0
+    }
+}
+
+impl Add for Estimate {
+    type Output = Estimate;
+
+    fn add(self, other: Estimate) -> Estimate {
+        Estimate {
+            duration_us: self.duration_us + other.duration_us,
+            charge_nc: self.charge_nc + other.charge_nc,
+        }
+    }
+}
+
+impl AddAssign for Estimate {
+    fn add_assign(&mut self, other: Estimate) {
+        *self = *self + other;
+    }
+}
+
+impl Sum for Estimate {
+    fn sum<I: Iterator<Item = Estimate>>(iter: I) -> Estimate {
+        iter.fold(Estimate::default(), Add::add)
+    }
+}
+
+/// Prints as e.g. `15.132 s, 52.361 mC`
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:03} s, {}.{:03} mC",
+            self.duration_us / 1_000_000,
+            self.duration_us / 1_000 % 1_000,
+            self.charge_nc / 1_000_000,
+            self.charge_nc / 1_000 % 1_000
+        )
+    }
+}
+
+/// Currents and step durations of the controller and panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Model {
+    /// Supply current while the clock runs, in microamperes
+    pub clock_ua: u32,
+    /// Current the booster and regulators add while the analog block is on
+    pub analog_ua: u32,
+    /// Current the panel adds while the waveform drives it
+    pub drive_ua: u32,
+    /// Time to turn the clock on or off, in microseconds
+    pub clock_us: u32,
+    /// Time for the booster to ramp up
+    pub analog_on_us: u32,
+    /// Time for the booster to discharge
+    pub analog_off_us: u32,
+    /// Time to read the internal temperature sensor
+    pub load_temperature_us: u32,
+    /// Time to load a LUT from OTP
+    pub load_lut_us: u32,
+    /// Length of the OTP display mode 1 waveform at room temperature, in milliseconds
+    pub otp_mode_1_ms: u32,
+    /// Length of the OTP display mode 2 waveform at room temperature, in milliseconds
+    pub otp_mode_2_ms: u32,
+    /// Temperature down to which the OTP waveforms keep their length, in °C
+    pub room_celsius: i8,
+    /// How much longer the OTP waveforms get per °C below `room_celsius`, in percent
+    pub cold_slowdown_percent: u32,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Model {
+            clock_ua: 250,
+            analog_ua: 1_200,
+            drive_ua: 2_000,
+            clock_us: 100,
+            analog_on_us: 20_000,
+            analog_off_us: 10_000,
+            load_temperature_us: 5_000,
+            load_lut_us: 2_000,
+            otp_mode_1_ms: 15_000,
+            otp_mode_2_ms: 3_000,
+            room_celsius: 20,
+            cold_slowdown_percent: 4,
+        }
+    }
+}
+
+impl Model {
+    /// Estimate a refresh run with DISP_CTRL2 set to `sequence`.
+    ///
+    /// `lut` is the LUT written with `WRITE_LUT`, if any, which the waveform uses unless
+    /// the sequence loads the LUT from OTP. `celsius` is the temperature the OTP LUT is
+    /// picked for.
+    pub fn estimate(&self, sequence: u8, lut: Option<&[u8; LUT_SIZE]>, celsius: i8) -> Estimate {
+        let clock = self.clock_ua;
+        let analog = clock + self.analog_ua;
+        let waveform_us = match lut.filter(|_| sequence & LOAD_LUT == 0) {
+            Some(lut) => lut_duration_us(lut),
+            None if sequence & MODE_2 != 0 => self.otp_duration_us(self.otp_mode_2_ms, celsius),
+            None => self.otp_duration_us(self.otp_mode_1_ms, celsius),
+        };
+
+        [
+            (CLOCK_ON, u64::from(self.clock_us), clock),
+            (ANALOG_ON, self.analog_on_us.into(), analog),
+            (LOAD_TEMPERATURE, self.load_temperature_us.into(), clock),
+            (LOAD_LUT, self.load_lut_us.into(), clock),
+            (DISPLAY, waveform_us, analog + self.drive_ua),
+            (ANALOG_OFF, self.analog_off_us.into(), analog),
+            (CLOCK_OFF, self.clock_us.into(), clock),
+        ]
+        .into_iter()
+        .filter(|&(step, _, _)| sequence & step != 0)
+        .map(|(_, duration_us, current_ua)| Estimate {
+            duration_us,
+            // µA × µs is pC
+            charge_nc: duration_us * u64::from(current_ua) / 1_000,
+        })
+        .sum()
+    }
+
+    /// Length of an OTP waveform at `celsius`, in microseconds
+    fn otp_duration_us(&self, room_ms: u32, celsius: i8) -> u64 {
+        let below = u64::from(
+            self.room_celsius
+                .saturating_sub(celsius)
+                .max(0)
+                .unsigned_abs(),
+        );
+        let percent = 100 + below * u64::from(self.cold_slowdown_percent);
+        u64::from(room_ms) * 1_000 * percent / 100
+    }
+}
+
+/// Length of the waveform in a LUT, in microseconds.
+///
+/// Each group runs its phases A and B, repeated SRAB + 1 times, and C and D, repeated
+/// SRCD + 1 times, RP + 1 times over. The model takes a frame rate of 25 Hz plus 25 Hz per
+/// step of the group's frame rate setting, the high nibble of an FR byte being the even
+/// group.
+pub fn lut_duration_us(lut: &[u8; LUT_SIZE]) -> u64 {
+    (0..LUT_GROUPS)
+        .map(|group| {
+            let timing: [u8; 7] = lut[LUT_TIMING + group * 7..][..7].try_into().unwrap();
+            let [a, b, ab_repeat, c, d, cd_repeat, repeat] = timing.map(u64::from);
+            let frames = ((a + b) * (ab_repeat + 1) + (c + d) * (cd_repeat + 1)) * (repeat + 1);
+            let setting = lut[LUT_FRAME_RATE + group / 2] >> (4 * (1 - group % 2)) & 0x0F;
+            let frame_rate_hz = 25 * (u64::from(setting) + 1);
+            frames * 1_000_000 / frame_rate_hz
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flag::Flag;
+    use crate::lut::GRAY4;
+    use std::string::ToString;
+
+    #[test]
+    fn lut_duration_counts_frames() {
+        // 40 frames in group 0 and 24 in group 1, at 75 Hz
+        assert_eq!(64 * 1_000_000 / 75, lut_duration_us(&GRAY4));
+        assert_eq!(0, lut_duration_us(&[0; LUT_SIZE]));
+    }
+
+    #[test]
+    fn estimate_adds_up_the_steps() {
+        let model = Model::default();
+        let estimate = model.estimate(Flag::DISPLAY_MODE_1, None, 25);
+        assert_eq!(15_037_200, estimate.duration_us);
+        // 51.75 mC of it is the waveform drawing 3.45 mA for 15 s
+        assert_eq!(51_795_300, estimate.charge_nc);
+        assert_eq!("15.037 s, 51.795 mC", estimate.to_string());
+
+        let custom = model.estimate(Flag::DISPLAY_MODE_CUSTOM_LUT, Some(&GRAY4), 25);
+        assert_eq!(
+            u64::from(model.clock_us + model.analog_on_us + model.analog_off_us + model.clock_us)
+                + lut_duration_us(&GRAY4),
+            custom.duration_us
+        );
+        assert_eq!(
+            estimate,
+            model.estimate(Flag::DISPLAY_MODE_1, Some(&GRAY4), 25),
+            "the OTP LUT replaces a written one"
+        );
+    }
+
+    #[test]
+    fn cold_slows_otp_waveforms() {
+        let model = Model::default();
+        let waveform = |celsius| model.estimate(DISPLAY, None, celsius).duration_us;
+        assert_eq!(15_000_000, waveform(20));
+        assert_eq!(15_000_000, waveform(40));
+        assert_eq!(27_000_000, waveform(0));
+        assert_eq!(
+            lut_duration_us(&GRAY4),
+            model.estimate(DISPLAY, Some(&GRAY4), 0).duration_us,
+            "written LUTs don't depend on the temperature"
+        );
+    }
+}
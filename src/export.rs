@@ -0,0 +1,347 @@
+//! Images of display contents for snapshot tests and documentation
+//!
+//! An [`Image`] holds either the colors of a [`Display`] as drawn, with rotation and
+//! polarity undone, or the panel image of its RAM planes. It is written as PPM with red
+//! in color, as PBM with only black set, or as PNG with the `png` feature. PPM and PNG
+//! files are also read back to compare against golden images.
+
+use crate::color::{PlaneBits, Polarity, TriColor};
+use crate::graphics::Display;
+use crate::{HEIGHT, WIDTH};
+use embedded_graphics_core::geometry::{OriginDimensions, Point};
+use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::string::String;
+use std::vec::Vec;
+
+/// Pixels of a tri-color image, row by row
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<TriColor>,
+}
+
+impl Image {
+    /// Image of the pixels in `pixels`, which must hold `width * height` colors
+    pub fn new(width: u32, height: u32, pixels: Vec<TriColor>) -> Self {
+        assert_eq!(width as usize * height as usize, pixels.len());
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Contents of a display in its drawing coordinates
+    pub fn from_display(display: &Display) -> Self {
+        let size = display.size();
+        let pixels = (0..size.height as i32)
+            .flat_map(|y| (0..size.width as i32).map(move |x| Point::new(x, y)))
+            .map(|point| display.get_pixel(point).unwrap_or(TriColor::White))
+            .collect();
+        Image::new(size.width, size.height, pixels)
+    }
+
+    /// Panel image of BW and red RAM planes, as laid out by `Display::black_data` and
+    /// `Display::red_data` without hardware rotation
+    pub fn from_planes(black: &[u8], red: &[u8], polarity: Polarity) -> Self {
+        let bytes_per_row = WIDTH as usize / 8;
+        assert!(black.len() >= bytes_per_row * HEIGHT as usize && red.len() >= black.len());
+        let pixels = (0..HEIGHT as usize)
+            .flat_map(|y| (0..WIDTH as usize).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let index = y * bytes_per_row + x / 8;
+                let bit = 0x80 >> (x % 8);
+                polarity.decode(PlaneBits {
+                    bw: black[index] & bit != 0,
+                    red: red[index] & bit != 0,
+                })
+            })
+            .collect();
+        Image::new(WIDTH.into(), HEIGHT.into(), pixels)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Color of a pixel, `None` outside the image
+    pub fn pixel(&self, x: u32, y: u32) -> Option<TriColor> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[(y * self.width + x) as usize])
+        } else {
+            None
+        }
+    }
+
+    pub fn pixels(&self) -> &[TriColor] {
+        &self.pixels
+    }
+
+    /// Write a binary PPM, red pixels in red
+    pub fn write_ppm(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        let data: Vec<u8> = self.pixels.iter().flat_map(|&c| rgb(c)).collect();
+        writer.write_all(&data)
+    }
+
+    /// Write a binary PBM, only black pixels are set
+    pub fn write_pbm(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "P4\n{} {}\n", self.width, self.height)?;
+        let mut data = Vec::new();
+        for row in self.pixels.chunks(self.width as usize) {
+            for byte in row.chunks(8) {
+                data.push(byte.iter().enumerate().fold(0, |acc, (i, &color)| {
+                    if color == TriColor::Black {
+                        acc | 0x80 >> i
+                    } else {
+                        acc
+                    }
+                }));
+            }
+        }
+        writer.write_all(&data)
+    }
+
+    /// Write an 8 bit RGB PNG, red pixels in red
+    #[cfg(any(test, feature = "png"))]
+    pub fn write_png(&self, writer: impl Write) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<u8> = self.pixels.iter().flat_map(|&c| rgb(c)).collect();
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .map_err(io::Error::other)
+    }
+
+    /// Read a binary PPM, each pixel becomes the nearest palette color
+    pub fn read_ppm(reader: impl Read) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0u32; 3];
+        if next_token(&mut reader)? != "P6" {
+            return Err(invalid("not a binary PPM"));
+        }
+        for value in header.iter_mut() {
+            *value = next_token(&mut reader)?
+                .parse()
+                .map_err(|_| invalid("bad PPM header"))?;
+        }
+        let [width, height, max] = header;
+        if max != 255 {
+            return Err(invalid("PPM is not 8 bit"));
+        }
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or_else(|| invalid("PPM too large"))?;
+        // grow with the data actually there rather than trusting the header
+        let mut data = Vec::new();
+        reader.take(len as u64).read_to_end(&mut data)?;
+        if data.len() != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "PPM shorter than its header says",
+            ));
+        }
+        let pixels = data
+            .chunks_exact(3)
+            .map(|c| Rgb888::new(c[0], c[1], c[2]).into())
+            .collect();
+        Ok(Image::new(width, height, pixels))
+    }
+
+    /// Read a PNG, each pixel becomes the nearest palette color
+    #[cfg(any(test, feature = "png"))]
+    pub fn read_png(reader: impl Read) -> io::Result<Self> {
+        let mut decoder = png::Decoder::new(BufReader::new(reader));
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(io::Error::other)?;
+        let mut data = std::vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut data).map_err(io::Error::other)?;
+        let channels = frame.color_type.samples();
+        let pixels = data[..frame.buffer_size()]
+            .chunks_exact(channels)
+            .map(|c| match *c {
+                [gray] | [gray, _] => Rgb888::new(gray, gray, gray).into(),
+                [r, g, b, ..] => Rgb888::new(r, g, b).into(),
+                [] => TriColor::White,
+            })
+            .collect();
+        Ok(Image::new(frame.width, frame.height, pixels))
+    }
+
+    /// Open a PNG or, with any other extension, a PPM file
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = fs::File::open(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(any(test, feature = "png"))]
+            Some("png") => Image::read_png(file),
+            _ => Image::read_ppm(file),
+        }
+    }
+
+    /// Save as PPM, PBM or, with the `png` feature, PNG depending on the file extension
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("pbm") => self.write_pbm(&mut file)?,
+            #[cfg(any(test, feature = "png"))]
+            Some("png") => self.write_png(&mut file)?,
+            _ => self.write_ppm(&mut file)?,
+        }
+        file.flush()
+    }
+
+    /// Number of pixels that differ from `other`, every pixel if the sizes differ
+    pub fn mismatches(&self, other: &Image) -> usize {
+        if (self.width, self.height) != (other.width, other.height) {
+            return self.pixels.len().max(other.pixels.len());
+        }
+        self.pixels
+            .iter()
+            .zip(&other.pixels)
+            .filter(|(a, b)| a != b)
+            .count()
+    }
+
+    /// Number of pixels that differ from the golden PNG or PPM at `path`
+    pub fn compare_golden(&self, path: impl AsRef<Path>) -> io::Result<usize> {
+        let golden = Image::open(path)?;
+        Ok(self.mismatches(&golden))
+    }
+}
+
+fn rgb(color: TriColor) -> [u8; 3] {
+    let c = Rgb888::from(color);
+    [c.r(), c.g(), c.b()]
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Next whitespace separated token of a PNM header, skipping comments
+fn next_token(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut token = String::new();
+    let mut byte = [0u8];
+    loop {
+        reader.read_exact(&mut byte)?;
+        match byte[0] {
+            b'#' => {
+                let mut comment = Vec::new();
+                reader.read_until(b'\n', &mut comment)?;
+            }
+            b if b.is_ascii_whitespace() => {
+                if !token.is_empty() {
+                    return Ok(token);
+                }
+            }
+            b => token.push(char::from(b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::DisplayRotation;
+    use embedded_graphics::prelude::*;
+    use embedded_graphics::primitives::{PrimitiveStyle, Rectangle};
+
+    fn draw(display: &mut Display) {
+        display.clear(TriColor::White);
+        display
+            .draw_iter([
+                Pixel(Point::new(0, 0), TriColor::Black),
+                Pixel(Point::new(9, 1), TriColor::Red),
+            ])
+            .unwrap();
+    }
+
+    #[test]
+    fn image_is_in_drawing_coordinates() {
+        for rotation in [
+            DisplayRotation::Rotate0,
+            DisplayRotation::Rotate90,
+            DisplayRotation::Rotate180,
+            DisplayRotation::Rotate270,
+        ] {
+            for hardware_rotation in [false, true] {
+                let mut display = Display::new();
+                display.set_rotation(rotation);
+                display.set_hardware_rotation(hardware_rotation);
+                draw(&mut display);
+                let image = Image::from_display(&display);
+                assert_eq!(Some(TriColor::Black), image.pixel(0, 0));
+                assert_eq!(Some(TriColor::Red), image.pixel(9, 1));
+                assert_eq!(Some(TriColor::White), image.pixel(1, 0));
+            }
+        }
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let mut display = Display::new();
+        draw(&mut display);
+        Rectangle::new(Point::new(50, 60), Size::new(20, 10))
+            .into_styled(PrimitiveStyle::with_fill(TriColor::Red))
+            .draw(&mut display)
+            .unwrap();
+        let image = Image::from_display(&display);
+
+        let mut ppm = Vec::new();
+        image.write_ppm(&mut ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n200 200\n255\n"));
+        let read = Image::read_ppm(&ppm[..]).unwrap();
+        assert_eq!(0, image.mismatches(&read));
+    }
+
+    #[test]
+    fn ppm_header_cannot_claim_huge_images() {
+        let kind = |ppm: &[u8]| Image::read_ppm(ppm).unwrap_err().kind();
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            kind(b"P6\n4294967295 4294967295\n255\n")
+        );
+        assert_eq!(
+            io::ErrorKind::UnexpectedEof,
+            kind(b"P6\n1000000 1000000\n255\n\xFF\xFF\xFF")
+        );
+    }
+
+    #[test]
+    fn pbm_sets_black_pixels() {
+        let mut display = Display::new();
+        draw(&mut display);
+        let mut pbm = Vec::new();
+        Image::from_display(&display).write_pbm(&mut pbm).unwrap();
+
+        let data = &pbm[b"P4\n200 200\n".len()..];
+        assert_eq!(200 * 25, data.len());
+        assert_eq!(0x80, data[0]);
+        assert_eq!(0x00, data[25 + 1]);
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut display = Display::new();
+        draw(&mut display);
+        let image = Image::from_display(&display);
+
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_eq!(image, Image::read_png(&png[..]).unwrap());
+    }
+}
@@ -1,8 +1,36 @@
 pub struct Flag;
 impl Flag {
+    pub const DATA_ENTRY_DECRY_DECRX: u8 = 0x00;
+    pub const DATA_ENTRY_DECRY_INCRX: u8 = 0x01;
+    pub const DATA_ENTRY_INCRY_DECRX: u8 = 0x02;
     pub const DATA_ENTRY_INCRY_INCRX: u8 = 0x03;
+    pub const DATA_ENTRY_Y_FIRST: u8 = 0x04;
+    pub const DRIVER_CONTROL_TB: u8 = 0x01;
+    pub const DRIVER_CONTROL_SM: u8 = 0x02;
+    pub const DRIVER_CONTROL_GD: u8 = 0x04;
     pub const INTERNAL_TEMP_SENSOR: u8 = 0x80;
+    pub const BORDER_WAVEFORM_GS_TRANSITION: u8 = 0x00;
+    pub const BORDER_WAVEFORM_FIX_LEVEL: u8 = 0x40;
+    pub const BORDER_WAVEFORM_VCOM: u8 = 0x80;
+    pub const BORDER_WAVEFORM_HIZ: u8 = 0xC0;
+    pub const BORDER_WAVEFORM_VSS: u8 = 0x00;
+    pub const BORDER_WAVEFORM_VSH1: u8 = 0x10;
+    pub const BORDER_WAVEFORM_VSL: u8 = 0x20;
+    pub const BORDER_WAVEFORM_VSH2: u8 = 0x30;
+    #[deprecated(
+        note = "this is follow LUT1, use BORDER_WAVEFORM_GS_FOLLOW_LUT | BORDER_WAVEFORM_LUT1"
+    )]
     pub const BORDER_WAVEFORM_FOLLOW_LUT: u8 = 0x05;
+    pub const BORDER_WAVEFORM_GS_FOLLOW_LUT: u8 = 0x04;
+    pub const BORDER_WAVEFORM_LUT0: u8 = 0x00;
     pub const BORDER_WAVEFORM_LUT1: u8 = 0x01;
+    pub const BORDER_WAVEFORM_LUT2: u8 = 0x02;
+    pub const BORDER_WAVEFORM_LUT3: u8 = 0x03;
     pub const DISPLAY_MODE_1: u8 = 0xF7;
+    pub const DISPLAY_MODE_CUSTOM_LUT: u8 = 0xC7;
+    pub const DISP_CTRL1_BYPASS_BW: u8 = 0x04;
+    pub const DISP_CTRL1_INVERSE_BW: u8 = 0x08;
+    pub const DISP_CTRL1_BYPASS_RED: u8 = 0x40;
+    pub const DISP_CTRL1_INVERSE_RED: u8 = 0x80;
+    pub const DISP_CTRL1_SOURCE_S8_S167: u8 = 0x80;
 }
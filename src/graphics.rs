@@ -1,10 +1,12 @@
-use crate::color::TriColor;
+use crate::color::{PlaneBits, Polarity, TriColor};
+use crate::config::PanelConfig;
 use crate::{HEIGHT, WIDTH};
 use embedded_graphics::framebuffer::{buffer_size, Framebuffer};
 use embedded_graphics_core::draw_target::DrawTarget;
-use embedded_graphics_core::geometry::{OriginDimensions, Size};
+use embedded_graphics_core::geometry::{OriginDimensions, Point, Size};
 use embedded_graphics_core::pixelcolor::raw::RawU1;
-use embedded_graphics_core::pixelcolor::{raw, BinaryColor};
+use embedded_graphics_core::pixelcolor::{raw, BinaryColor, Gray2, GrayColor};
+use embedded_graphics_core::primitives::Rectangle;
 use embedded_graphics_core::Pixel;
 
 /// Rotation of the display.
@@ -21,25 +23,223 @@ pub enum DisplayRotation {
     /// Rotate 270 degress clockwise, recommend
     Rotate270,
 }
-pub struct Display {
-    black_fbuf: Framebuffer<
-        BinaryColor,
-        RawU1,
-        raw::LittleEndian,
-        { WIDTH as usize },
-        { HEIGHT as usize },
-        { buffer_size::<BinaryColor>(WIDTH as usize, HEIGHT as usize) },
-    >,
-    red_fbuf: Framebuffer<
-        BinaryColor,
-        RawU1,
-        raw::LittleEndian,
-        { WIDTH as usize },
-        { HEIGHT as usize },
-        { buffer_size::<BinaryColor>(WIDTH as usize, HEIGHT as usize) },
-    >,
+/// Size of one RAM plane in bytes
+pub const BUFFER_SIZE: usize = buffer_size::<BinaryColor>(WIDTH as usize, HEIGHT as usize);
+
+const BYTES_PER_ROW: usize = (WIDTH as usize).div_ceil(8);
+
+type PlaneBuffer = Framebuffer<
+    BinaryColor,
+    RawU1,
+    raw::LittleEndian,
+    { WIDTH as usize },
+    { HEIGHT as usize },
+    BUFFER_SIZE,
+>;
+
+/// One RAM plane and the bounding box of the bits changed in it.
+///
+/// The box is in buffer coordinates and widened to whole bytes, so it can be uploaded
+/// as a RAM window as is.
+struct Plane {
+    fbuf: PlaneBuffer,
+    dirty: Option<Rectangle>,
+}
+
+impl Plane {
+    fn new() -> Self {
+        Plane {
+            fbuf: PlaneBuffer::new(),
+            dirty: None,
+        }
+    }
+
+    fn data(&self) -> &[u8; BUFFER_SIZE] {
+        self.fbuf.data()
+    }
+
+    fn set_bits(&mut self, index: usize, mask: u8, value: bool) {
+        let byte = &mut self.fbuf.data_mut()[index];
+        let old = *byte;
+        set_bits(byte, mask, value);
+        if *byte != old {
+            let (column, row) = (index % BYTES_PER_ROW, index / BYTES_PER_ROW);
+            self.mark_dirty(column, row, column, row);
+        }
+    }
+
+    /// Set or clear a rectangle of buffer pixels a byte at a time
+    fn fill(&mut self, rect: (u32, u32, u32, u32), lsb_first: bool, value: bool) {
+        let (x0, y0, x1, y1) = rect;
+        let (first, last) = (x0 as usize / 8, x1 as usize / 8);
+        let mut changed = false;
+
+        for row in self
+            .fbuf
+            .data_mut()
+            .chunks_exact_mut(BYTES_PER_ROW)
+            .skip(y0 as usize)
+            .take((y1 - y0 + 1) as usize)
+        {
+            for (column, byte) in row[first..=last].iter_mut().enumerate() {
+                let start = if column == 0 { x0 % 8 } else { 0 };
+                let end = if first + column == last { x1 % 8 } else { 7 };
+                let mask = (0xFF >> start) & (0xFF << (7 - end));
+                let mask = if lsb_first {
+                    u8::reverse_bits(mask)
+                } else {
+                    mask
+                };
+                let old = *byte;
+                set_bits(byte, mask, value);
+                changed |= *byte != old;
+            }
+        }
+        if changed {
+            self.mark_dirty(first, y0 as usize, last, y1 as usize);
+        }
+    }
+
+    /// Set the bits of a byte under `mask` to `bits`, growing the inclusive range of
+    /// byte indices in `changed` if that changes the byte
+    fn write_byte(
+        &mut self,
+        index: usize,
+        mask: u8,
+        bits: u8,
+        changed: &mut Option<(usize, usize)>,
+    ) {
+        let byte = &mut self.fbuf.data_mut()[index];
+        let new = (*byte & !mask) | (bits & mask);
+        if new != *byte {
+            *byte = new;
+            let (first, last) = changed.unwrap_or((index, index));
+            *changed = Some((first.min(index), last.max(index)));
+        }
+    }
+
+    /// Mark a range of bytes in one row from `write_byte` dirty
+    fn mark_dirty_bytes(&mut self, changed: Option<(usize, usize)>) {
+        if let Some((first, last)) = changed {
+            let row = first / BYTES_PER_ROW;
+            self.mark_dirty(first % BYTES_PER_ROW, row, last % BYTES_PER_ROW, row);
+        }
+    }
+
+    fn fill_all(&mut self, value: bool) {
+        let fill = if value { 0xFF } else { 0x00 };
+        let data = self.fbuf.data_mut();
+        if data.iter().any(|&byte| byte != fill) {
+            data.fill(fill);
+            self.mark_dirty(0, 0, BYTES_PER_ROW - 1, HEIGHT as usize - 1);
+        }
+    }
+
+    /// Grow the dirty box by an inclusive range of byte columns and rows
+    fn mark_dirty(
+        &mut self,
+        first_column: usize,
+        first_row: usize,
+        last_column: usize,
+        last_row: usize,
+    ) {
+        let mut top_left = Point::new(first_column as i32 * 8, first_row as i32);
+        let mut bottom_right = Point::new(last_column as i32 * 8 + 7, last_row as i32);
+        if let Some(dirty) = self.dirty {
+            top_left = top_left.component_min(dirty.top_left);
+            bottom_right = bottom_right.component_max(dirty.bottom_right().unwrap_or(top_left));
+        }
+        self.dirty = Some(Rectangle::with_corners(top_left, bottom_right));
+    }
+}
+
+/// Parts of the RAM planes changed since the dirty region was last taken.
+///
+/// Boxes are in buffer coordinates, widened to whole bytes. They can be passed to
+/// `Ssd1681::update_bw_region` and `Ssd1681::update_red_region`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DirtyRegion {
+    pub black: Option<Rectangle>,
+    pub red: Option<Rectangle>,
+}
+
+/// Rotation state shared by the display buffers.
+#[derive(Clone, Copy, Debug, Default)]
+struct Orientation {
     rotation: DisplayRotation,
-    is_inverted: bool,
+    hardware_rotation: bool,
+}
+
+impl Orientation {
+    /// Byte index and bit mask of a pixel, `None` if it is off the display
+    fn position(&self, x: u32, y: u32) -> Option<(usize, u8)> {
+        let (index, bit) = if self.hardware_rotation {
+            find_native_position(x, y, WIDTH as u32, HEIGHT as u32, self.rotation)
+        } else {
+            find_position(x, y, WIDTH as u32, HEIGHT as u32, self.rotation)
+        }?;
+        Some((index as usize, bit))
+    }
+
+    /// Buffer coordinates of a pixel, `None` if it is off the display
+    fn native_point(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        if self.hardware_rotation {
+            find_native_rotation(x, y, WIDTH as u32, HEIGHT as u32, self.rotation)
+        } else {
+            find_rotation(x, y, WIDTH as u32, HEIGHT as u32, self.rotation)
+        }
+    }
+
+    /// Whether pixels are stored LSB first in each byte
+    fn lsb_first(&self) -> bool {
+        self.hardware_rotation && mirrors_x(self.rotation)
+    }
+
+    /// Direction the buffer X moves in when the drawing X grows, `None` when drawing rows
+    /// are buffer columns
+    fn row_step(&self) -> Option<isize> {
+        match (self.rotation, self.hardware_rotation) {
+            (DisplayRotation::Rotate90 | DisplayRotation::Rotate270, _) => None,
+            (DisplayRotation::Rotate180, false) => Some(-1),
+            _ => Some(1),
+        }
+    }
+
+    /// Buffer rectangle, as inclusive corners, covered by the on-screen part of an area
+    fn native_rect(&self, area: &Rectangle) -> Option<(u32, u32, u32, u32)> {
+        let area = visible(area, self.size());
+        let bottom_right = area.bottom_right()?;
+        let (ax, ay) = self.native_point(area.top_left.x as u32, area.top_left.y as u32)?;
+        let (bx, by) = self.native_point(bottom_right.x as u32, bottom_right.y as u32)?;
+        Some((ax.min(bx), ay.min(by), ax.max(bx), ay.max(by)))
+    }
+
+    /// Coordinates of a point if it lies on the display
+    fn clip(&self, point: Point) -> Option<(u32, u32)> {
+        let size = self.size();
+        <(u32, u32)>::try_from(point)
+            .ok()
+            .filter(|&(x, y)| x < size.width && y < size.height)
+    }
+
+    fn size(&self) -> Size {
+        //if display is rotated 90 deg or 270 then swap height and width
+        match self.rotation {
+            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
+                Size::new(WIDTH.into(), HEIGHT.into())
+            }
+            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
+                Size::new(HEIGHT.into(), WIDTH.into())
+            }
+        }
+    }
+}
+
+pub struct Display {
+    black_fbuf: Plane,
+    red_fbuf: Plane,
+    orientation: Orientation,
+    polarity: Polarity,
 }
 
 impl Default for Display {
@@ -49,107 +249,354 @@ impl Default for Display {
 }
 impl Display {
     pub fn new() -> Self {
+        Self::with_config(&PanelConfig::default())
+    }
+
+    /// Buffers for a panel driven with `config`, e.g. `Ssd1681::config`, encoding
+    /// colors with its polarity
+    pub fn with_config(config: &PanelConfig) -> Self {
         Display {
-            black_fbuf: Framebuffer::<
-                BinaryColor,
-                _,
-                _,
-                { WIDTH as usize },
-                { HEIGHT as usize },
-                { buffer_size::<BinaryColor>(WIDTH as usize, HEIGHT as usize) },
-            >::new(),
-            red_fbuf: Framebuffer::<
-                BinaryColor,
-                _,
-                _,
-                { WIDTH as usize },
-                { HEIGHT as usize },
-                { buffer_size::<BinaryColor>(WIDTH as usize, HEIGHT as usize) },
-            >::new(),
-            rotation: DisplayRotation::default(),
-            is_inverted: true,
+            black_fbuf: Plane::new(),
+            red_fbuf: Plane::new(),
+            orientation: Orientation::default(),
+            polarity: config.polarity,
         }
     }
 
     /// Clear the buffers, filling them a single color.
     pub fn clear(&mut self, color: TriColor) {
-        let (black, red) = match color {
-            TriColor::White => (BinaryColor::On, BinaryColor::Off),
-            TriColor::Black => (BinaryColor::Off, BinaryColor::Off),
-            TriColor::Red => (BinaryColor::On, BinaryColor::On),
-        };
+        let bits = self.polarity.encode(color);
 
-        let _ = self.black_fbuf.clear(black);
-        let _ = self.red_fbuf.clear(red);
+        self.black_fbuf.fill_all(bits.bw);
+        self.red_fbuf.fill_all(bits.red);
     }
 
-    #[allow(dead_code)]
     fn set_pixel(&mut self, x: u32, y: u32, color: TriColor) {
-        let (index, bit) = find_position(x, y, WIDTH as u32, HEIGHT as u32, self.rotation);
-        let index = index as usize;
+        let Some((index, bit)) = self.orientation.position(x, y) else {
+            return;
+        };
+        let bits = self.polarity.encode(color);
 
-        match color {
-            TriColor::Black => {
-                self.black_fbuf.data_mut()[index] &= !bit;
-                self.red_fbuf.data_mut()[index] &= !bit;
-            }
-            TriColor::White => {
-                self.black_fbuf.data_mut()[index] |= bit;
-                self.red_fbuf.data_mut()[index] &= !bit;
-            }
-            TriColor::Red => {
-                self.black_fbuf.data_mut()[index] |= bit;
-                self.red_fbuf.data_mut()[index] |= bit;
-            }
+        self.black_fbuf.set_bits(index, bit, bits.bw);
+        self.red_fbuf.set_bits(index, bit, bits.red);
+    }
+
+    /// Color of a pixel as drawn, `None` if the point is off the display
+    pub fn get_pixel(&self, point: Point) -> Option<TriColor> {
+        let (x, y) = self.orientation.clip(point)?;
+        let (index, bit) = self.orientation.position(x, y)?;
+
+        Some(self.polarity.decode(PlaneBits {
+            bw: self.black_fbuf.data()[index] & bit != 0,
+            red: self.red_fbuf.data()[index] & bit != 0,
+        }))
+    }
+
+    /// Parts of the buffers changed since the last `take_dirty`
+    pub fn dirty_region(&self) -> DirtyRegion {
+        DirtyRegion {
+            black: self.black_fbuf.dirty,
+            red: self.red_fbuf.dirty,
+        }
+    }
+
+    /// Return the changed parts of the buffers and start tracking afresh
+    pub fn take_dirty(&mut self) -> DirtyRegion {
+        DirtyRegion {
+            black: self.black_fbuf.dirty.take(),
+            red: self.red_fbuf.dirty.take(),
         }
     }
 
     pub fn set_rotation(&mut self, rotation: DisplayRotation) {
-        self.rotation = rotation;
+        self.orientation.rotation = rotation;
     }
 
     pub fn rotation(&self) -> DisplayRotation {
-        self.rotation
+        self.orientation.rotation
+    }
+
+    /// Leave mirroring to the controller instead of rotating every pixel in software.
+    ///
+    /// The buffers then only work with a driver rotated the same way through
+    /// `Ssd1681::set_rotation`.
+    pub fn set_hardware_rotation(&mut self, enabled: bool) {
+        self.orientation.hardware_rotation = enabled;
     }
 
+    pub fn hardware_rotation(&self) -> bool {
+        self.orientation.hardware_rotation
+    }
+
+    /// How colors are encoded in the buffers, from `PanelConfig::polarity`
+    pub fn polarity(&self) -> Polarity {
+        self.polarity
+    }
+
+    /// Whether white is a set BW bit
+    #[deprecated(note = "use `polarity().bw_inverted`, which is the opposite")]
     pub fn is_inverted(&self) -> bool {
-        self.is_inverted
+        !self.polarity.bw_inverted
     }
 
-    pub fn black_data(&self) -> &[u8; buffer_size::<BinaryColor>(WIDTH as usize, HEIGHT as usize)] {
+    pub fn black_data(&self) -> &[u8; BUFFER_SIZE] {
         self.black_fbuf.data()
     }
-    pub fn red_data(&self) -> &[u8; buffer_size::<BinaryColor>(WIDTH as usize, HEIGHT as usize)] {
+    pub fn red_data(&self) -> &[u8; BUFFER_SIZE] {
         self.red_fbuf.data()
     }
 }
-fn find_rotation(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u32) {
-    let nx;
-    let ny;
-    match rotation {
-        DisplayRotation::Rotate0 => {
-            nx = x;
-            ny = y;
-        }
-        DisplayRotation::Rotate90 => {
-            nx = width - 1 - y;
-            ny = x;
-        }
-        DisplayRotation::Rotate180 => {
-            nx = width - 1 - x;
-            ny = height - 1 - y;
+
+/// Single plane buffer for black and white panels.
+///
+/// `BinaryColor::On` is black, matching `From<BinaryColor> for TriColor`.
+pub struct MonoDisplay {
+    fbuf: Plane,
+    orientation: Orientation,
+    polarity: Polarity,
+}
+
+impl Default for MonoDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl MonoDisplay {
+    pub fn new() -> Self {
+        Self::with_config(&PanelConfig::default())
+    }
+
+    /// See `Display::with_config`
+    pub fn with_config(config: &PanelConfig) -> Self {
+        MonoDisplay {
+            fbuf: Plane::new(),
+            orientation: Orientation::default(),
+            polarity: config.polarity,
         }
-        DisplayRotation::Rotate270 => {
-            nx = y;
-            ny = height - 1 - x;
+    }
+
+    /// Clear the buffer, filling it a single color.
+    pub fn clear(&mut self, color: BinaryColor) {
+        self.fbuf.fill_all(self.polarity.encode(color.into()).bw);
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: BinaryColor) {
+        let Some((index, bit)) = self.orientation.position(x, y) else {
+            return;
+        };
+
+        self.fbuf
+            .set_bits(index, bit, self.polarity.encode(color.into()).bw);
+    }
+
+    /// Part of the buffer changed since the last `take_dirty`, see `Display::dirty_region`
+    pub fn dirty_region(&self) -> Option<Rectangle> {
+        self.fbuf.dirty
+    }
+
+    /// Return the changed part of the buffer and start tracking afresh
+    pub fn take_dirty(&mut self) -> Option<Rectangle> {
+        self.fbuf.dirty.take()
+    }
+
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.orientation.rotation = rotation;
+    }
+
+    pub fn rotation(&self) -> DisplayRotation {
+        self.orientation.rotation
+    }
+
+    /// See `Display::set_hardware_rotation`
+    pub fn set_hardware_rotation(&mut self, enabled: bool) {
+        self.orientation.hardware_rotation = enabled;
+    }
+
+    pub fn hardware_rotation(&self) -> bool {
+        self.orientation.hardware_rotation
+    }
+
+    pub fn polarity(&self) -> Polarity {
+        self.polarity
+    }
+
+    pub fn data(&self) -> &[u8; BUFFER_SIZE] {
+        self.fbuf.data()
+    }
+}
+
+/// Four level grayscale buffer, split into bitplanes for the BW and red RAM.
+///
+/// The planes are meant for `Ssd1681::display_gray_frame`, which refreshes with the
+/// `lut::GRAY4` waveform.
+pub struct GrayDisplay {
+    low_fbuf: Plane,
+    high_fbuf: Plane,
+    orientation: Orientation,
+}
+
+impl Default for GrayDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl GrayDisplay {
+    pub fn new() -> Self {
+        GrayDisplay {
+            low_fbuf: Plane::new(),
+            high_fbuf: Plane::new(),
+            orientation: Orientation::default(),
         }
     }
-    (nx, ny)
+
+    /// Clear the buffers, filling them a single gray level.
+    pub fn clear(&mut self, color: Gray2) {
+        let (low, high) = gray_bits(color);
+
+        self.low_fbuf.fill_all(low);
+        self.high_fbuf.fill_all(high);
+    }
+
+    fn set_pixel(&mut self, x: u32, y: u32, color: Gray2) {
+        let Some((index, bit)) = self.orientation.position(x, y) else {
+            return;
+        };
+        let (low, high) = gray_bits(color);
+
+        self.low_fbuf.set_bits(index, bit, low);
+        self.high_fbuf.set_bits(index, bit, high);
+    }
+
+    pub fn set_rotation(&mut self, rotation: DisplayRotation) {
+        self.orientation.rotation = rotation;
+    }
+
+    pub fn rotation(&self) -> DisplayRotation {
+        self.orientation.rotation
+    }
+
+    /// See `Display::set_hardware_rotation`
+    pub fn set_hardware_rotation(&mut self, enabled: bool) {
+        self.orientation.hardware_rotation = enabled;
+    }
+
+    pub fn hardware_rotation(&self) -> bool {
+        self.orientation.hardware_rotation
+    }
+
+    /// Low bit of each pixel, for the BW RAM
+    pub fn bw_data(&self) -> &[u8; BUFFER_SIZE] {
+        self.low_fbuf.data()
+    }
+
+    /// High bit of each pixel, for the red RAM
+    pub fn red_data(&self) -> &[u8; BUFFER_SIZE] {
+        self.high_fbuf.data()
+    }
 }
 
-fn find_position(x: u32, y: u32, width: u32, height: u32, rotation: DisplayRotation) -> (u32, u8) {
-    let (nx, ny) = find_rotation(x, y, width, height, rotation);
-    (nx / 8 + ((width + 7) / 8) * ny, 0x80 >> (nx % 8))
+/// Part of an area on a display of `size`.
+///
+/// Unlike `Rectangle::intersection` this can't overflow for areas reaching past `i32::MAX`.
+pub(crate) fn visible(area: &Rectangle, size: Size) -> Rectangle {
+    let (columns, rows) = (area.columns(), area.rows());
+    let x = columns.start.max(0)..columns.end.min(size.width as i32);
+    let y = rows.start.max(0)..rows.end.min(size.height as i32);
+    if x.is_empty() || y.is_empty() {
+        return Rectangle::zero();
+    }
+    Rectangle::new(
+        Point::new(x.start, y.start),
+        Size::new(x.len() as u32, y.len() as u32),
+    )
+}
+
+/// Buffer coordinates of a point drawn on a rotated display, `None` if it is off the display
+fn find_rotation(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> Option<(u32, u32)> {
+    let (nx, ny) = match rotation {
+        DisplayRotation::Rotate0 => (x, y),
+        DisplayRotation::Rotate90 => (width.checked_sub(1)?.checked_sub(y)?, x),
+        DisplayRotation::Rotate180 => (
+            width.checked_sub(1)?.checked_sub(x)?,
+            height.checked_sub(1)?.checked_sub(y)?,
+        ),
+        DisplayRotation::Rotate270 => (y, height.checked_sub(1)?.checked_sub(x)?),
+    };
+    (nx < width && ny < height).then_some((nx, ny))
+}
+
+/// Byte index and bit mask of a point drawn on a rotated display
+fn find_position(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> Option<(u32, u8)> {
+    let (nx, ny) = find_rotation(x, y, width, height, rotation)?;
+    Some((nx / 8 + width.div_ceil(8) * ny, 0x80 >> (nx % 8)))
+}
+
+/// Buffer coordinates when the controller mirrors the buffer, only transposing is left
+fn find_native_rotation(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> Option<(u32, u32)> {
+    let (nx, ny) = match rotation {
+        DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => (x, y),
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => (y, x),
+    };
+    (nx < width && ny < height).then_some((nx, ny))
+}
+
+/// Whether the controller walks the X address backwards for a hardware rotation
+fn mirrors_x(rotation: DisplayRotation) -> bool {
+    matches!(
+        rotation,
+        DisplayRotation::Rotate90 | DisplayRotation::Rotate180
+    )
+}
+
+/// Position of a pixel when the controller mirrors the buffer through its data entry mode.
+///
+/// Only the transpose for 90 and 270 degrees is left to do, and since the controller
+/// walks bytes backwards when it mirrors X the bits are stored LSB first.
+fn find_native_position(
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    rotation: DisplayRotation,
+) -> Option<(u32, u8)> {
+    let (nx, ny) = find_native_rotation(x, y, width, height, rotation)?;
+    let bit = if mirrors_x(rotation) {
+        0x01 << (nx % 8)
+    } else {
+        0x80 >> (nx % 8)
+    };
+    Some((nx / 8 + width.div_ceil(8) * ny, bit))
+}
+
+/// Values of the BW and red RAM bits for a gray level, selecting LUT `luma`
+fn gray_bits(color: Gray2) -> (bool, bool) {
+    (color.luma() & 0b01 != 0, color.luma() & 0b10 != 0)
+}
+
+fn set_bits(byte: &mut u8, mask: u8, value: bool) {
+    if value {
+        *byte |= mask;
+    } else {
+        *byte &= !mask;
+    }
 }
 
 impl DrawTarget for Display {
@@ -161,35 +608,761 @@ impl DrawTarget for Display {
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
         for Pixel(point, color) in pixels.into_iter() {
-            match color {
-                TriColor::White => {
-                    self.black_fbuf.draw_iter([Pixel(point, BinaryColor::On)])?;
-                    self.red_fbuf.draw_iter([Pixel(point, BinaryColor::Off)])?;
-                }
-                TriColor::Black => {
-                    self.black_fbuf
-                        .draw_iter([Pixel(point, BinaryColor::Off)])?;
-                    self.red_fbuf.draw_iter([Pixel(point, BinaryColor::Off)])?;
-                }
-                TriColor::Red => {
-                    self.black_fbuf.draw_iter([Pixel(point, BinaryColor::On)])?;
-                    self.red_fbuf.draw_iter([Pixel(point, BinaryColor::On)])?;
-                }
+            if let Some((x, y)) = self.orientation.clip(point) {
+                self.set_pixel(x, y, color);
             }
         }
         Ok(())
     }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        self.fill_clipped(area, colors);
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        if let Some(rect) = self.orientation.native_rect(area) {
+            let lsb_first = self.orientation.lsb_first();
+            let bits = self.polarity.encode(color);
+            self.black_fbuf.fill(rect, lsb_first, bits.bw);
+            self.red_fbuf.fill(rect, lsb_first, bits.red);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        Display::clear(self, color);
+        Ok(())
+    }
+}
+
+impl Display {
+    /// Clipped row by row version of `DrawTarget::fill_contiguous` that skips the
+    /// per pixel bounds check of `draw_iter`
+    fn fill_clipped<I>(&mut self, area: &Rectangle, colors: I)
+    where
+        I: IntoIterator<Item = TriColor>,
+    {
+        let drawable = visible(area, self.size());
+        let width = area.size.width as usize;
+        let visible_width = drawable.size.width as usize;
+        // colors left of the display, the difference can exceed i32
+        let skip = (i64::from(drawable.top_left.x) - i64::from(area.top_left.x)) as usize;
+        let rest = width.saturating_sub(skip + visible_width);
+        let mut colors = colors.into_iter();
+
+        for y in area.rows() {
+            let complete = if drawable.rows().contains(&y) {
+                skip_colors(&mut colors, skip)
+                    && self.fill_row(
+                        drawable.top_left.x as u32,
+                        y as u32,
+                        &mut colors,
+                        visible_width,
+                    )
+                    && skip_colors(&mut colors, rest)
+            } else {
+                skip_colors(&mut colors, width)
+            };
+            // huge areas take a long time to walk once the colors run out
+            if !complete {
+                break;
+            }
+        }
+    }
+
+    /// Draw `count` pixels from `(x, y)` to the right, packed into whole bytes where the
+    /// row is a row of the buffer. Rows of a display rotated by 90 or 270 degrees are
+    /// buffer columns and are drawn pixel by pixel. Returns false if the colors ran out.
+    fn fill_row(
+        &mut self,
+        x: u32,
+        y: u32,
+        colors: &mut impl Iterator<Item = TriColor>,
+        count: usize,
+    ) -> bool {
+        let (Some(step), Some((start, row))) = (
+            self.orientation.row_step(),
+            self.orientation.native_point(x, y),
+        ) else {
+            for x in (x..).take(count) {
+                let Some(color) = colors.next() else {
+                    return false;
+                };
+                self.set_pixel(x, y, color);
+            }
+            return true;
+        };
+        let polarity = self.polarity;
+        let lsb_first = self.orientation.lsb_first();
+        // the next pixel is in the lower bit, until the byte is done
+        let shift_right = (step > 0) != lsb_first;
+        let first_bit = if shift_right { 0x80 } else { 0x01 };
+        let mut bit: u8 = if lsb_first {
+            0x01 << (start % 8)
+        } else {
+            0x80 >> (start % 8)
+        };
+        let mut index = row as usize * BYTES_PER_ROW + start as usize / 8;
+        let (mut mask, mut black, mut red) = (0u8, 0u8, 0u8);
+        let (mut black_changed, mut red_changed) = (None, None);
+        let mut complete = true;
+
+        for _ in 0..count {
+            let Some(color) = colors.next() else {
+                complete = false;
+                break;
+            };
+            let bits = polarity.encode(color);
+            mask |= bit;
+            if bits.bw {
+                black |= bit;
+            }
+            if bits.red {
+                red |= bit;
+            }
+            bit = if shift_right { bit >> 1 } else { bit << 1 };
+            if bit == 0 {
+                self.black_fbuf
+                    .write_byte(index, mask, black, &mut black_changed);
+                self.red_fbuf.write_byte(index, mask, red, &mut red_changed);
+                index = index.wrapping_add_signed(step);
+                (bit, mask, black, red) = (first_bit, 0, 0, 0);
+            }
+        }
+        if mask != 0 {
+            self.black_fbuf
+                .write_byte(index, mask, black, &mut black_changed);
+            self.red_fbuf.write_byte(index, mask, red, &mut red_changed);
+        }
+        self.black_fbuf.mark_dirty_bytes(black_changed);
+        self.red_fbuf.mark_dirty_bytes(red_changed);
+        complete
+    }
+}
+
+/// Drop `count` colors, false if there weren't that many
+fn skip_colors(colors: &mut impl Iterator<Item = TriColor>, count: usize) -> bool {
+    count == 0 || colors.nth(count - 1).is_some()
 }
 
 impl OriginDimensions for Display {
     fn size(&self) -> Size {
-        //if display is rotated 90 deg or 270 then swap height and width
-        match self.rotation() {
-            DisplayRotation::Rotate0 | DisplayRotation::Rotate180 => {
-                Size::new(WIDTH.into(), HEIGHT.into())
+        self.orientation.size()
+    }
+}
+
+impl DrawTarget for MonoDisplay {
+    type Color = BinaryColor;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels.into_iter() {
+            if let Some((x, y)) = self.orientation.clip(point) {
+                self.set_pixel(x, y, color);
             }
-            DisplayRotation::Rotate90 | DisplayRotation::Rotate270 => {
-                Size::new(HEIGHT.into(), WIDTH.into())
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        if let Some(rect) = self.orientation.native_rect(area) {
+            let lsb_first = self.orientation.lsb_first();
+            let bits = self.polarity.encode(color.into());
+            self.fbuf.fill(rect, lsb_first, bits.bw);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        MonoDisplay::clear(self, color);
+        Ok(())
+    }
+}
+
+impl OriginDimensions for MonoDisplay {
+    fn size(&self) -> Size {
+        self.orientation.size()
+    }
+}
+
+impl DrawTarget for GrayDisplay {
+    type Color = Gray2;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels.into_iter() {
+            if let Some((x, y)) = self.orientation.clip(point) {
+                self.set_pixel(x, y, color);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        if let Some(rect) = self.orientation.native_rect(area) {
+            let lsb_first = self.orientation.lsb_first();
+            let (low, high) = gray_bits(color);
+            self.low_fbuf.fill(rect, lsb_first, low);
+            self.high_fbuf.fill(rect, lsb_first, high);
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        GrayDisplay::clear(self, color);
+        Ok(())
+    }
+}
+
+impl OriginDimensions for GrayDisplay {
+    fn size(&self) -> Size {
+        self.orientation.size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::testing::{draw_pattern, ROTATIONS};
+    use crate::color::BwyColor;
+    use crate::config::DataEntryMode;
+    use crate::snapshot::assert_snapshot;
+    use embedded_graphics::draw_target::DrawTargetExt;
+    use embedded_graphics::mono_font::ascii::{FONT_10X20, FONT_6X9};
+    use embedded_graphics::mono_font::{MonoTextStyle, MonoTextStyleBuilder};
+    use embedded_graphics::primitives::{
+        Circle, Ellipse, Line, Primitive, PrimitiveStyle, PrimitiveStyleBuilder, RoundedRectangle,
+        Triangle,
+    };
+    use embedded_graphics::text::Text;
+    use embedded_graphics_core::primitives::PointsIter;
+    use embedded_graphics_core::Drawable;
+
+    /// Lay out a buffer the way the controller stores it in RAM for a data entry mode
+    fn write_ram(buffer: &[u8], mode: DataEntryMode) -> [u8; BUFFER_SIZE] {
+        let bytes_per_row = WIDTH as usize / 8;
+        let mut ram = [0; BUFFER_SIZE];
+        for (i, byte) in buffer.iter().enumerate() {
+            let (mut col, mut row) = (i % bytes_per_row, i / bytes_per_row);
+            if !mode.x_increments() {
+                col = bytes_per_row - 1 - col;
+            }
+            if !mode.y_increments() {
+                row = HEIGHT as usize - 1 - row;
+            }
+            ram[row * bytes_per_row + col] = *byte;
+        }
+        ram
+    }
+
+    #[test]
+    fn hardware_rotation_matches_software_rotation() {
+        for (rotation, mode) in ROTATIONS.into_iter().zip([
+            DataEntryMode::IncrYIncrX,
+            DataEntryMode::IncrYDecrX,
+            DataEntryMode::DecrYDecrX,
+            DataEntryMode::DecrYIncrX,
+        ]) {
+            let mut software = Display::new();
+            software.set_rotation(rotation);
+            draw_pattern(&mut software);
+
+            let mut hardware = Display::new();
+            hardware.set_rotation(rotation);
+            hardware.set_hardware_rotation(true);
+            draw_pattern(&mut hardware);
+
+            assert_eq!(
+                software.black_data(),
+                &write_ram(hardware.black_data(), mode),
+                "{rotation:?}"
+            );
+            assert_eq!(
+                software.red_data(),
+                &write_ram(hardware.red_data(), mode),
+                "{rotation:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rotation_moves_pixels() {
+        let mut display = Display::new();
+        display.clear(TriColor::White);
+        display.set_rotation(DisplayRotation::Rotate90);
+        Pixel(Point::new(0, 0), TriColor::Black)
+            .draw(&mut display)
+            .unwrap();
+        // the top left corner of a display rotated clockwise is the top right of the panel
+        assert_eq!(0xFE, display.black_data()[24]);
+    }
+
+    /// Bytes worked out by hand from the datasheet RAM layout rather than `write_ram`: X
+    /// address `n` holds sources `8n` to `8n + 7` in bits D7 to D0 whatever the data entry
+    /// mode, which only sets the order the addresses are written in.
+    #[test]
+    fn hardware_rotation_matches_datasheet_ram_layout() {
+        let draw = |rotation, points: &[(i32, i32)]| {
+            let mut display = Display::new();
+            display.set_rotation(rotation);
+            display.set_hardware_rotation(true);
+            display.clear(TriColor::White);
+            for &(x, y) in points {
+                Pixel(Point::new(x, y), TriColor::Black)
+                    .draw(&mut display)
+                    .unwrap();
+            }
+            display
+        };
+
+        // Y increments and X decrements: the first byte goes to X address 24 of gate 0,
+        // the top right corner, with S199 in D0. The next row of the buffer starts there
+        // again on gate 1.
+        let display = draw(DisplayRotation::Rotate90, &[(0, 0), (1, 0)]);
+        assert_eq!(0xFE, display.black_data()[0]);
+        assert_eq!(0xFE, display.black_data()[25]);
+
+        // both decrement: the first byte goes to X address 24 of gate 199 and the second
+        // to X address 23, with S190 in D1. Drawing point (9, 0) is panel point (190, 199).
+        let display = draw(DisplayRotation::Rotate180, &[(9, 0)]);
+        assert_eq!(0xFF, display.black_data()[0]);
+        assert_eq!(0xFD, display.black_data()[1]);
+
+        // Y decrements and X increments: the first byte goes to X address 0 of gate 199,
+        // with S0 in D7. Drawing point (0, 0) is panel point (0, 199).
+        let display = draw(DisplayRotation::Rotate270, &[(0, 0)]);
+        assert_eq!(0x7F, display.black_data()[0]);
+    }
+
+    #[test]
+    fn mono_display_matches_black_plane() {
+        for rotation in ROTATIONS {
+            let mut mono = MonoDisplay::new();
+            mono.set_rotation(rotation);
+            mono.clear(BinaryColor::Off);
+            Line::new(Point::new(3, 7), Point::new(150, 60))
+                .into_styled(PrimitiveStyle::with_stroke(BinaryColor::On, 2))
+                .draw(&mut mono)
+                .unwrap();
+
+            let mut display = Display::new();
+            display.set_rotation(rotation);
+            display.clear(TriColor::White);
+            Line::new(Point::new(3, 7), Point::new(150, 60))
+                .into_styled(PrimitiveStyle::with_stroke(TriColor::Black, 2))
+                .draw(&mut display)
+                .unwrap();
+
+            assert_eq!(display.black_data(), mono.data(), "{rotation:?}");
+        }
+    }
+
+    /// Draw pixel by pixel through `draw_iter`, the path the fast fills must match
+    fn fill_slow(display: &mut Display, area: &Rectangle, color: TriColor) {
+        display
+            .draw_iter(area.points().map(|point| Pixel(point, color)))
+            .unwrap();
+    }
+
+    const AREAS: [(i32, i32, u32, u32); 5] = [
+        (0, 0, 200, 200),
+        (3, 5, 1, 1),
+        (13, 21, 37, 90),
+        (-20, 150, 60, 80),
+        (190, -4, 30, 9),
+    ];
+
+    #[test]
+    fn fill_solid_matches_draw_iter() {
+        for rotation in ROTATIONS {
+            for hardware_rotation in [false, true] {
+                let mut fast = Display::new();
+                let mut slow = Display::new();
+                for display in [&mut fast, &mut slow] {
+                    display.set_rotation(rotation);
+                    display.set_hardware_rotation(hardware_rotation);
+                    display.clear(TriColor::White);
+                }
+                for (i, (x, y, width, height)) in AREAS.into_iter().enumerate() {
+                    let area = Rectangle::new(Point::new(x, y), Size::new(width, height));
+                    let color = [TriColor::Red, TriColor::Black, TriColor::White][i % 3];
+                    fast.fill_solid(&area, color).unwrap();
+                    fill_slow(&mut slow, &area, color);
+                }
+                assert_eq!(slow.black_data(), fast.black_data(), "{rotation:?}");
+                assert_eq!(slow.red_data(), fast.red_data(), "{rotation:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn fill_contiguous_matches_draw_iter() {
+        let colors = [
+            TriColor::Red,
+            TriColor::Black,
+            TriColor::White,
+            TriColor::Black,
+            TriColor::White,
+        ];
+        for rotation in ROTATIONS {
+            for hardware_rotation in [false, true] {
+                let mut fast = Display::new();
+                let mut slow = Display::new();
+                for display in [&mut fast, &mut slow] {
+                    display.set_rotation(rotation);
+                    display.set_hardware_rotation(hardware_rotation);
+                    display.clear(TriColor::White);
+                    display.take_dirty();
+                }
+                for (x, y, width, height) in AREAS {
+                    let area = Rectangle::new(Point::new(x, y), Size::new(width, height));
+                    let colors = colors.iter().copied().cycle();
+                    fast.fill_contiguous(&area, colors.clone()).unwrap();
+                    slow.draw_iter(area.points().zip(colors).map(|(p, c)| Pixel(p, c)))
+                        .unwrap();
+                }
+                assert_eq!(slow.black_data(), fast.black_data(), "{rotation:?}");
+                assert_eq!(slow.red_data(), fast.red_data(), "{rotation:?}");
+                assert_eq!(slow.dirty_region(), fast.dirty_region(), "{rotation:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn dirty_region_tracks_changed_bytes() {
+        let mut display = Display::new();
+        display.clear(TriColor::White);
+        let full = Rectangle::new(Point::zero(), Size::new(200, 200));
+        assert_eq!(Some(full), display.take_dirty().black);
+        assert_eq!(DirtyRegion::default(), display.dirty_region());
+
+        // redrawing the same colors changes nothing
+        display.clear(TriColor::White);
+        Pixel(Point::new(10, 10), TriColor::White)
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(DirtyRegion::default(), display.dirty_region());
+
+        Pixel(Point::new(10, 10), TriColor::Black)
+            .draw(&mut display)
+            .unwrap();
+        Pixel(Point::new(30, 40), TriColor::Black)
+            .draw(&mut display)
+            .unwrap();
+        let region = display.take_dirty();
+        assert_eq!(
+            Some(Rectangle::with_corners(
+                Point::new(8, 10),
+                Point::new(31, 40)
+            )),
+            region.black
+        );
+        assert_eq!(None, region.red);
+
+        display
+            .fill_solid(
+                &Rectangle::new(Point::new(50, 60), Size::new(3, 2)),
+                TriColor::Red,
+            )
+            .unwrap();
+        let region = display.take_dirty();
+        // red keeps the BW bit of white
+        assert_eq!(None, region.black);
+        assert_eq!(
+            Some(Rectangle::with_corners(
+                Point::new(48, 60),
+                Point::new(55, 61)
+            )),
+            region.red
+        );
+    }
+
+    /// Color the panel shows for a pixel of the buffers
+    fn glass(display: &Display, x: usize, y: usize) -> TriColor {
+        let index = y * BYTES_PER_ROW + x / 8;
+        let bit = 0x80 >> (x % 8);
+        display.polarity().decode(PlaneBits {
+            bw: display.black_data()[index] & bit != 0,
+            red: display.red_data()[index] & bit != 0,
+        })
+    }
+
+    #[test]
+    fn colors_end_up_on_the_glass() {
+        let mut display = Display::new();
+        for background in [TriColor::White, TriColor::Black, TriColor::Red] {
+            display.clear(background);
+            display
+                .draw_iter([
+                    Pixel(Point::new(0, 0), TriColor::Red),
+                    Pixel(Point::new(1, 0), TriColor::Black),
+                    Pixel(Point::new(2, 0), TriColor::White),
+                ])
+                .unwrap();
+            let row: [TriColor; 4] = core::array::from_fn(|x| glass(&display, x, 0));
+            assert_eq!(
+                [TriColor::Red, TriColor::Black, TriColor::White, background],
+                row
+            );
+        }
+    }
+
+    #[test]
+    fn polarity_is_configurable() {
+        let mut display = Display::with_config(&PanelConfig {
+            polarity: Polarity {
+                bw_inverted: true,
+                red_active_low: true,
+            },
+            ..PanelConfig::default()
+        });
+        display.clear(TriColor::White);
+        assert_eq!(0x00, display.black_data()[0]);
+        assert_eq!(0xFF, display.red_data()[0]);
+
+        display
+            .color_converted()
+            .draw_iter([
+                Pixel(Point::new(0, 0), BwyColor::Yellow),
+                Pixel(Point::new(1, 0), BwyColor::Black),
+            ])
+            .unwrap();
+        assert_eq!(TriColor::Red, glass(&display, 0, 0));
+        assert_eq!(TriColor::Black, glass(&display, 1, 0));
+        assert_eq!(TriColor::White, glass(&display, 2, 0));
+        assert_eq!(0b0111_1111, display.red_data()[0]);
+    }
+
+    #[test]
+    fn tri_color_image() {
+        use embedded_graphics::image::{Image, ImageRaw};
+        use embedded_graphics::mock_display::MockDisplay;
+
+        // 2 bits per pixel: black, white, red, black / red, red, white, white
+        let data = [0b00_01_10_00, 0b10_10_01_01];
+        let raw = ImageRaw::<TriColor>::new(&data, 4);
+
+        let mut mock = MockDisplay::new();
+        Image::new(&raw, Point::zero()).draw(&mut mock).unwrap();
+        mock.assert_pattern(&["BWRB", "RRWW"]);
+
+        let mut display = Display::new();
+        display.clear(TriColor::White);
+        Image::new(&raw, Point::new(8, 0))
+            .draw(&mut display)
+            .unwrap();
+        assert_eq!(0b0110_1111, display.black_data()[1]);
+        assert_eq!(0b1111_1111, display.black_data()[26]);
+        assert_eq!(0b0010_0000, display.red_data()[1]);
+        assert_eq!(0b1100_0000, display.red_data()[26]);
+    }
+
+    #[test]
+    fn gray_display_splits_bitplanes() {
+        let mut display = GrayDisplay::new();
+        display.clear(Gray2::WHITE);
+        assert!(display.bw_data().iter().all(|&byte| byte == 0xFF));
+        assert!(display.red_data().iter().all(|&byte| byte == 0xFF));
+
+        for luma in 0..4 {
+            Pixel(Point::new(luma, 0), Gray2::new(luma as u8))
+                .draw(&mut display)
+                .unwrap();
+        }
+        assert_eq!(0b0101_1111, display.bw_data()[0]);
+        assert_eq!(0b0011_1111, display.red_data()[0]);
+    }
+
+    #[test]
+    fn out_of_bounds_pixels_are_ignored() {
+        let mut display = Display::new();
+        display.clear(TriColor::White);
+        for rotation in ROTATIONS {
+            display.set_rotation(rotation);
+            display
+                .draw_iter([
+                    Pixel(Point::new(-1, 0), TriColor::Black),
+                    Pixel(Point::new(0, -1), TriColor::Black),
+                    Pixel(Point::new(200, 0), TriColor::Black),
+                    Pixel(Point::new(0, 200), TriColor::Black),
+                ])
+                .unwrap();
+        }
+        assert!(display.black_data().iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn rotation_snapshots() {
+        let style = MonoTextStyle::new(&FONT_6X9, TriColor::Black);
+        for (rotation, name) in
+            ROTATIONS
+                .into_iter()
+                .zip(["rotate0", "rotate90", "rotate180", "rotate270"])
+        {
+            let mut display = Display::new();
+            display.set_rotation(rotation);
+            display.clear(TriColor::White);
+            Text::new("Top left", Point::new(4, 10), style)
+                .draw(&mut display)
+                .unwrap();
+            Rectangle::new(Point::new(4, 20), Size::new(40, 10))
+                .into_styled(PrimitiveStyle::with_fill(TriColor::Red))
+                .draw(&mut display)
+                .unwrap();
+            Line::new(Point::new(0, 0), Point::new(199, 199))
+                .into_styled(PrimitiveStyle::with_stroke(TriColor::Black, 1))
+                .draw(&mut display)
+                .unwrap();
+            assert_snapshot(name, &display);
+        }
+    }
+
+    #[test]
+    fn text_snapshot() {
+        let mut display = Display::new();
+        display.clear(TriColor::White);
+        Text::new(
+            "This is a\nmultiline\nHello World!",
+            Point::new(15, 15),
+            MonoTextStyle::new(&FONT_6X9, TriColor::Black),
+        )
+        .draw(&mut display)
+        .unwrap();
+        let style = MonoTextStyleBuilder::new()
+            .font(&FONT_10X20)
+            .text_color(TriColor::White)
+            .background_color(TriColor::Red)
+            .build();
+        Text::new("Red 10x20", Point::new(15, 100), style)
+            .draw(&mut display)
+            .unwrap();
+        assert_snapshot("text", &display);
+    }
+
+    #[test]
+    fn primitives_snapshot() {
+        let mut display = Display::new();
+        display.clear(TriColor::White);
+        let black = PrimitiveStyle::with_stroke(TriColor::Black, 3);
+        let red = PrimitiveStyle::with_fill(TriColor::Red);
+        Circle::new(Point::new(10, 10), 60)
+            .into_styled(black)
+            .draw(&mut display)
+            .unwrap();
+        Circle::new(Point::new(25, 25), 30)
+            .into_styled(red)
+            .draw(&mut display)
+            .unwrap();
+        RoundedRectangle::with_equal_corners(
+            Rectangle::new(Point::new(90, 10), Size::new(100, 60)),
+            Size::new(12, 12),
+        )
+        .into_styled(
+            PrimitiveStyleBuilder::new()
+                .stroke_color(TriColor::Black)
+                .stroke_width(2)
+                .fill_color(TriColor::Red)
+                .build(),
+        )
+        .draw(&mut display)
+        .unwrap();
+        Triangle::new(
+            Point::new(10, 190),
+            Point::new(60, 100),
+            Point::new(110, 190),
+        )
+        .into_styled(red)
+        .draw(&mut display)
+        .unwrap();
+        Ellipse::new(Point::new(120, 110), Size::new(70, 40))
+            .into_styled(PrimitiveStyle::with_fill(TriColor::Black))
+            .draw(&mut display)
+            .unwrap();
+        Line::new(Point::new(120, 190), Point::new(195, 160))
+            .into_styled(black)
+            .draw(&mut display)
+            .unwrap();
+        assert_snapshot("primitives", &display);
+    }
+
+    #[test]
+    fn every_point_maps_to_a_unique_bit() {
+        for rotation in ROTATIONS {
+            for hardware_rotation in [false, true] {
+                let orientation = Orientation {
+                    rotation,
+                    hardware_rotation,
+                };
+                let size = orientation.size();
+                let mut seen = [0u8; BUFFER_SIZE];
+                for y in 0..size.height {
+                    for x in 0..size.width {
+                        let (index, bit) = orientation.position(x, y).unwrap();
+                        assert_eq!(1, bit.count_ones());
+                        assert_eq!(0, seen[index] & bit, "({x}, {y}) {rotation:?}");
+                        seen[index] |= bit;
+                    }
+                }
+                assert!(seen.iter().all(|&byte| byte == 0xFF));
+            }
+        }
+    }
+
+    #[test]
+    fn out_of_range_points_are_rejected() {
+        let (width, height) = (WIDTH as u32, HEIGHT as u32);
+        let outside = [width, width + 7, u32::MAX / 2, u32::MAX];
+        for rotation in ROTATIONS {
+            for &far in &outside {
+                for near in [0, 1, width - 1] {
+                    for (x, y) in [(far, near), (near, far), (far, far)] {
+                        assert_eq!(None, find_rotation(x, y, width, height, rotation));
+                        assert_eq!(None, find_position(x, y, width, height, rotation));
+                        assert_eq!(None, find_native_position(x, y, width, height, rotation));
+                    }
+                }
+            }
+            // an empty display has no pixels at all
+            assert_eq!(None, find_rotation(0, 0, 0, 0, rotation));
+        }
+    }
+
+    #[test]
+    fn extreme_coordinates_never_panic() {
+        let extremes = [i32::MIN, -1, 0, 199, 200, i32::MAX];
+        for rotation in ROTATIONS {
+            for hardware_rotation in [false, true] {
+                let mut display = Display::new();
+                display.set_rotation(rotation);
+                display.set_hardware_rotation(hardware_rotation);
+                display.clear(TriColor::White);
+                for x in extremes {
+                    for y in extremes {
+                        let point = Point::new(x, y);
+                        Pixel(point, TriColor::Black).draw(&mut display).unwrap();
+                        assert_eq!(
+                            (0..200).contains(&x) && (0..200).contains(&y),
+                            display.get_pixel(point).is_some()
+                        );
+                        for size in [Size::new(1, 1), Size::new(300, 300), Size::new(u32::MAX, 3)] {
+                            let area = Rectangle::new(point, size);
+                            display.fill_solid(&area, TriColor::Red).unwrap();
+                            display
+                                .fill_contiguous(&area, core::iter::repeat_n(TriColor::Black, 8))
+                                .unwrap();
+                        }
+                    }
+                }
             }
         }
     }
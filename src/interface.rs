@@ -7,7 +7,9 @@ use embedded_hal::{
 
 const RESET_DELAY_MS: u8 = 10;
 
+/// Errors are non-exhaustive, match them with a wildcard arm
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum DisplayError {
     InvalidFormatError,
     BusWriteError,
@@ -16,6 +18,7 @@ pub enum DisplayError {
     DataFormatNotImplemented,
     RSError,
     OutOfBoundsError,
+    InvalidConfigError,
 }
 
 pub(crate) struct DisplayInterface<SPI, CS, BUSY, DC, RST> {
@@ -1,11 +1,31 @@
 #![no_std]
 
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+
+#[cfg(any(test, feature = "std"))]
+pub mod bus;
 pub mod cmd;
 pub mod color;
+pub mod config;
+pub mod decode;
+pub mod dither;
 pub mod driver;
+#[cfg(any(test, feature = "std"))]
+pub mod emulator;
+pub mod energy;
+#[cfg(any(test, feature = "std"))]
+pub mod export;
 pub mod flag;
 pub mod graphics;
 pub mod interface;
+pub mod lut;
+#[cfg(any(test, feature = "std"))]
+pub mod mock;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+#[cfg(any(test, feature = "png"))]
+pub mod snapshot;
 
 /// Maximum display height this driver supports
 pub const HEIGHT: u8 = 200;
@@ -0,0 +1,39 @@
+//! Waveform lookup tables written with `Cmd::WRITE_LUT`
+//!
+//! A LUT holds the voltages of LUT0 to LUT3, picked per pixel by its red and BW RAM bits
+//! (`red << 1 | bw`), and of VCOM for 12 groups of 4 phases, followed by the phase lengths
+//! and repeats of each group, the frame rates and the gate XON settings.
+
+/// Size of a waveform LUT in bytes
+pub const LUT_SIZE: usize = 153;
+
+/// Four gray levels, LUT `n` shows `Gray2` luma `n`.
+///
+/// Group 0 resets every pixel to white, group 1 then drives black for 4, 2, 1 or 0 of its
+/// equally long phases.
+#[rustfmt::skip]
+pub const GRAY4: [u8; LUT_SIZE] = [
+    // VS: LUT0 (black), LUT1 (dark gray), LUT2 (light gray), LUT3 (white), VCOM
+    0x90, 0xAA, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x90, 0xA0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x90, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x90, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // TP: TPA, TPB, SRAB, TPC, TPD, SRCD, RP for groups 0 to 11
+    0x14, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x06, 0x06, 0x00, 0x06, 0x06, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // FR
+    0x22, 0x22, 0x22, 0x22, 0x22, 0x22,
+    // XON
+    0x00, 0x00, 0x00,
+];
@@ -0,0 +1,367 @@
+//! Recording mock of the SPI device, pins and delay for command sequence tests
+//!
+//! [`Mock`] hands out the [`bus`] peripherals `Ssd1681` takes and logs every byte written
+//! over SPI, split into commands and data by the level of DC, together with the CS, DC and
+//! RST transitions and the delays. The assertion helpers compare the log with the expected
+//! commands and their data.
+//!
+//! BUSY reads low unless a [`BusyTiming`] holds it high after a reset, `SW_RESET` or
+//! `MASTER_ACTIVATE`, for a number of polls or of simulated milliseconds. Time only
+//! passes in the mock delays, and the delays taken after a poll found BUSY high are
+//! counted as waiting.
+//!
+//! The bytes are also passed to an emulated controller, and every `MASTER_ACTIVATE` is
+//! estimated with an [`energy::Model`] from its registers: the `DISP_CTRL2` sequence, the
+//! LUT written with `WRITE_LUT` and the temperature set with [`Mock::set_temperature`] or
+//! `TEMP_WRITE`, so tests can report what their refreshes cost.
+//!
+//! ```
+//! use epd_ssd1681::cmd::Cmd;
+//! use epd_ssd1681::driver::Ssd1681;
+//! use epd_ssd1681::flag::Flag;
+//! use epd_ssd1681::mock::Mock;
+//!
+//! let mock = Mock::new();
+//! let (spi, cs, busy, dc, rst) = mock.interface();
+//! let mut ssd1681 = Ssd1681::new(spi, cs, busy, dc, rst, &mut mock.delay()).unwrap();
+//! mock.clear();
+//! ssd1681.display_frame(&mut mock.delay()).unwrap();
+//! mock.assert_transactions(&[
+//!     (Cmd::DISP_CTRL2, &[Flag::DISPLAY_MODE_1]),
+//!     (Cmd::MASTER_ACTIVATE, &[]),
+//! ]);
+//! ```
+
+use crate::bus::{self, Bus, Busy, Delay, Device, Line, Pin, Spi};
+use crate::cmd::Cmd;
+use crate::color::Polarity;
+use crate::decode::{self, Decoder};
+use crate::emulator::Controller;
+use crate::energy::{self, Estimate};
+use std::cell::{Ref, RefCell, RefMut};
+use std::rc::Rc;
+use std::vec::Vec;
+
+/// Something the driver did to the peripherals
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Byte written over SPI while DC was low
+    Command(u8),
+    /// Byte written over SPI while DC was high
+    Data(u8),
+    /// CS set high (`true`) or low
+    Cs(bool),
+    /// DC set high (`true`) or low
+    Dc(bool),
+    /// RST set high (`true`) or low
+    Rst(bool),
+    /// Delay in nanoseconds
+    Delay(u32),
+}
+
+/// How long BUSY stays high after an operation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BusyTime {
+    /// Not busy at all
+    #[default]
+    Idle,
+    /// BUSY reads high this many times
+    Polls(u32),
+    /// BUSY reads high until this many milliseconds of delays have passed
+    Millis(u32),
+}
+
+/// BUSY periods of the operations that keep the controller busy
+///
+/// Periods that overlap end with the one that ends last.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BusyTiming {
+    /// After RST goes high again
+    pub reset: BusyTime,
+    /// After `Cmd::SW_RESET`
+    pub sw_reset: BusyTime,
+    /// After `Cmd::MASTER_ACTIVATE`
+    pub activate: BusyTime,
+}
+
+/// Log of a [`Mock`]
+#[derive(Debug)]
+pub struct Log {
+    events: Vec<Event>,
+    timing: BusyTiming,
+    /// Simulated time, advanced by the delays
+    now_ns: u64,
+    busy_polls_left: u32,
+    busy_until_ns: u64,
+    /// Whether the last poll found BUSY high, so the next delay is spent waiting
+    polled_busy: bool,
+    busy_polls: u32,
+    busy_wait_ns: u64,
+    /// Registers the refreshes are estimated from
+    controller: Controller,
+    model: energy::Model,
+    refreshes: Vec<Estimate>,
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Log {
+            events: Vec::new(),
+            timing: BusyTiming::default(),
+            now_ns: 0,
+            busy_polls_left: 0,
+            busy_until_ns: 0,
+            polled_busy: false,
+            busy_polls: 0,
+            busy_wait_ns: 0,
+            controller: Controller::new(Polarity::default()),
+            model: energy::Model::default(),
+            refreshes: Vec::new(),
+        }
+    }
+}
+
+impl Log {
+    fn start_busy(&mut self, time: BusyTime) {
+        match time {
+            BusyTime::Idle => {}
+            BusyTime::Polls(polls) => self.busy_polls_left = self.busy_polls_left.max(polls),
+            BusyTime::Millis(ms) => {
+                self.busy_until_ns = self
+                    .busy_until_ns
+                    .max(self.now_ns + u64::from(ms) * 1_000_000)
+            }
+        }
+    }
+}
+
+impl Device for Log {
+    fn command(&mut self, command: u8) {
+        self.events.push(Event::Command(command));
+        match command {
+            Cmd::SW_RESET => self.start_busy(self.timing.sw_reset),
+            Cmd::MASTER_ACTIVATE => {
+                let estimate = self.controller.estimate(&self.model);
+                self.refreshes.push(estimate);
+                self.start_busy(self.timing.activate);
+            }
+            _ => {}
+        }
+        self.controller.command(command);
+    }
+
+    fn data(&mut self, byte: u8) {
+        self.events.push(Event::Data(byte));
+        self.controller.data(byte);
+    }
+
+    fn pin(&mut self, line: Line, high: bool) {
+        self.events.push(match line {
+            Line::Cs => Event::Cs(high),
+            Line::Dc => Event::Dc(high),
+            Line::Rst => Event::Rst(high),
+        });
+        if line == Line::Rst && high {
+            self.start_busy(self.timing.reset);
+        }
+        self.controller.pin(line, high);
+    }
+
+    fn busy(&mut self) -> bool {
+        let busy = self.busy_polls_left > 0 || self.now_ns < self.busy_until_ns;
+        self.busy_polls_left = self.busy_polls_left.saturating_sub(1);
+        self.polled_busy = busy;
+        if busy {
+            self.busy_polls += 1;
+        }
+        busy
+    }
+
+    fn delay(&mut self, ns: u32) {
+        self.events.push(Event::Delay(ns));
+        self.now_ns += u64::from(ns);
+        if self.polled_busy {
+            self.busy_wait_ns += u64::from(ns);
+            self.polled_busy = false;
+        }
+    }
+}
+
+/// Recording SPI device, pins and delay
+///
+/// Cloning the mock, or any of its handles, shares the same log.
+#[derive(Clone, Debug)]
+pub struct Mock {
+    bus: Rc<RefCell<Bus<Log>>>,
+}
+
+impl Default for Mock {
+    fn default() -> Self {
+        Mock {
+            bus: Bus::shared(Log::default()),
+        }
+    }
+}
+
+impl Mock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mock that holds BUSY high as set by `timing`
+    pub fn with_busy_timing(timing: BusyTiming) -> Self {
+        let mock = Self::default();
+        mock.log_mut().timing = timing;
+        mock
+    }
+
+    /// SPI device, CS, BUSY, DC and RST in the order `Ssd1681::new` takes them
+    pub fn interface(&self) -> (Spi, Pin, Busy, Pin, Pin) {
+        bus::interface(&self.bus)
+    }
+
+    /// Delay that is logged instead of slept
+    pub fn delay(&self) -> Delay {
+        bus::delay(&self.bus)
+    }
+
+    fn log(&self) -> Ref<'_, Log> {
+        Ref::map(self.bus.borrow(), |bus| &bus.device)
+    }
+
+    fn log_mut(&self) -> RefMut<'_, Log> {
+        RefMut::map(self.bus.borrow_mut(), |bus| &mut bus.device)
+    }
+
+    /// Everything logged since the last `clear`
+    pub fn events(&self) -> Vec<Event> {
+        self.log().events.clone()
+    }
+
+    /// Estimate refreshes with `model` instead of the default one
+    pub fn set_energy_model(&self, model: energy::Model) {
+        self.log_mut().model = model;
+    }
+
+    /// Temperature the internal sensor reads, in °C, 25 by default
+    pub fn set_temperature(&self, celsius: i8) {
+        self.log_mut().controller.set_temperature(celsius);
+    }
+
+    /// Forget the logged events and refreshes and reset the BUSY counters
+    pub fn clear(&self) {
+        let mut log = self.log_mut();
+        log.events.clear();
+        log.busy_polls = 0;
+        log.busy_wait_ns = 0;
+        log.refreshes.clear();
+    }
+
+    /// Estimate of each `MASTER_ACTIVATE` since the last `clear`
+    pub fn refreshes(&self) -> Vec<Estimate> {
+        self.log().refreshes.clone()
+    }
+
+    /// Estimate of all refreshes since the last `clear`
+    pub fn refresh_total(&self) -> Estimate {
+        self.log().refreshes.iter().copied().sum()
+    }
+
+    /// Number of polls that found BUSY high since the last `clear`
+    pub fn busy_polls(&self) -> u32 {
+        self.log().busy_polls
+    }
+
+    /// Time spent in delays after a poll found BUSY high since the last `clear`, in
+    /// nanoseconds
+    pub fn busy_wait_ns(&self) -> u64 {
+        self.log().busy_wait_ns
+    }
+
+    /// Simulated time since the mock was created, in nanoseconds
+    pub fn now_ns(&self) -> u64 {
+        self.log().now_ns
+    }
+
+    /// Bytes logged since the last `clear`, with the level of DC
+    fn bytes(&self) -> Vec<(bool, u8)> {
+        self.log()
+            .events
+            .iter()
+            .filter_map(|event| match *event {
+                Event::Command(byte) => Some((false, byte)),
+                Event::Data(byte) => Some((true, byte)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Commands logged since the last `clear`, decoded for printing
+    pub fn operations(&self) -> Vec<decode::Operation> {
+        Decoder::new(self.bytes()).collect()
+    }
+
+    /// Assert the commands and data logged since the last `clear`.
+    ///
+    /// Data sent before the first command is ignored. Differences are shown as decoded
+    /// operations.
+    #[track_caller]
+    pub fn assert_transactions(&self, expected: &[(u8, &[u8])]) {
+        let mut actual: Vec<(u8, Vec<u8>)> = Vec::new();
+        for (data, byte) in self.bytes() {
+            match actual.last_mut() {
+                Some((_, params)) if data => params.push(byte),
+                None if data => {}
+                _ => actual.push((byte, Vec::new())),
+            }
+        }
+        for (i, ((command, params), &expected)) in actual.iter().zip(expected).enumerate() {
+            let actual = (*command, &params[..]);
+            assert!(
+                actual == expected,
+                "transaction {i} differs\n  expected: {}\n    actual: {}",
+                decoded(expected),
+                decoded(actual),
+            );
+        }
+        assert!(
+            actual.len() == expected.len(),
+            "expected {} transactions, got {}{}",
+            expected.len(),
+            actual.len(),
+            actual
+                .get(expected.len())
+                .map(|(command, params)| std::format!(
+                    ", the first extra one is {}",
+                    decoded((*command, params))
+                ))
+                .unwrap_or_default()
+        );
+    }
+
+    /// Assert the events logged since the last `clear`
+    #[track_caller]
+    pub fn assert_events(&self, expected: &[Event]) {
+        assert_eq!(expected, &self.events()[..]);
+    }
+
+    /// Total delay logged since the last `clear`, in nanoseconds
+    pub fn delay_ns(&self) -> u64 {
+        self.log()
+            .events
+            .iter()
+            .map(|event| match event {
+                Event::Delay(ns) => u64::from(*ns),
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// A command and its data as the decoder prints them
+fn decoded((command, params): (u8, &[u8])) -> decode::Operation {
+    let bytes = params.iter().map(|&byte| (true, byte));
+    Decoder::new(core::iter::once((false, command)).chain(bytes))
+        .next()
+        .unwrap()
+}
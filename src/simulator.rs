@@ -0,0 +1,209 @@
+//! Preview of a [`Display`] in embedded-graphics-simulator
+//!
+//! [`Simulator`] renders the colors of a `Display` as drawn into a
+//! `SimulatorDisplay<Rgb888>`, using colors that look like an e-paper panel. It can also
+//! play an approximation of the flashing of a full refresh. The output is headless: an
+//! `OutputImage` from [`Simulator::output_image`] works in CI, and the frames can be shown
+//! in a window by enabling the `with-sdl` feature of `embedded-graphics-simulator`.
+
+use crate::color::TriColor;
+use crate::graphics::Display;
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{OriginDimensions, Point};
+use embedded_graphics_core::pixelcolor::Rgb888;
+use embedded_graphics_core::Pixel;
+use embedded_graphics_simulator::{OutputImage, OutputSettings, SimulatorDisplay};
+
+/// Screen colors of the three pixel colors
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    pub black: Rgb888,
+    pub white: Rgb888,
+    pub red: Rgb888,
+}
+
+impl Palette {
+    /// Colors of a tri-color e-paper panel: gray-white paper, dark ink and a muted red
+    pub const EPAPER: Palette = Palette {
+        black: Rgb888::new(0x22, 0x22, 0x26),
+        white: Rgb888::new(0xE4, 0xE2, 0xDA),
+        red: Rgb888::new(0xB4, 0x22, 0x1E),
+    };
+
+    /// Pure black, white and red
+    pub const PURE: Palette = Palette {
+        black: Rgb888::new(0x00, 0x00, 0x00),
+        white: Rgb888::new(0xFF, 0xFF, 0xFF),
+        red: Rgb888::new(0xFF, 0x00, 0x00),
+    };
+
+    pub fn color(&self, color: TriColor) -> Rgb888 {
+        match color {
+            TriColor::Black => self.black,
+            TriColor::White => self.white,
+            TriColor::Red => self.red,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::EPAPER
+    }
+}
+
+/// Simulated panel showing the contents of a [`Display`]
+pub struct Simulator {
+    palette: Palette,
+    flash: bool,
+    screen: SimulatorDisplay<Rgb888>,
+}
+
+impl Simulator {
+    /// Blank panel with the e-paper palette and no refresh flash
+    pub fn new() -> Self {
+        Simulator::with_palette(Palette::default())
+    }
+
+    pub fn with_palette(palette: Palette) -> Self {
+        let size = Display::new().size();
+        Simulator {
+            palette,
+            flash: false,
+            screen: SimulatorDisplay::with_default_color(size, palette.white),
+        }
+    }
+
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// Play the flashing of a full refresh in [`Simulator::refresh`]
+    pub fn set_flash(&mut self, enabled: bool) {
+        self.flash = enabled;
+    }
+
+    pub fn flash(&self) -> bool {
+        self.flash
+    }
+
+    /// Show the contents of `display` at once
+    pub fn show(&mut self, display: &Display) {
+        self.refresh(display, |_| {});
+    }
+
+    /// Show the contents of `display`, calling `frame` with each screen on the way.
+    ///
+    /// With the flash on, the panel shows the new image inverted, then black, then white
+    /// before the image itself, roughly as a full refresh of the panel does. Otherwise
+    /// `frame` is only called with the image.
+    pub fn refresh(&mut self, display: &Display, mut frame: impl FnMut(&SimulatorDisplay<Rgb888>)) {
+        if self.screen.size() != display.size() {
+            self.screen = SimulatorDisplay::with_default_color(display.size(), self.palette.white);
+        }
+        if self.flash {
+            self.draw(display, |color| match color {
+                TriColor::White => TriColor::Black,
+                _ => TriColor::White,
+            });
+            frame(&self.screen);
+            for color in [TriColor::Black, TriColor::White] {
+                self.screen.clear(self.palette.color(color)).unwrap();
+                frame(&self.screen);
+            }
+        }
+        self.draw(display, |color| color);
+        frame(&self.screen);
+    }
+
+    /// Simulated panel as last shown
+    pub fn screen(&self) -> &SimulatorDisplay<Rgb888> {
+        &self.screen
+    }
+
+    /// Image of the panel for saving as PNG, or for a window
+    pub fn output_image(&self, settings: &OutputSettings) -> OutputImage<Rgb888> {
+        self.screen.to_rgb_output_image(settings)
+    }
+
+    fn draw(&mut self, display: &Display, map: impl Fn(TriColor) -> TriColor) {
+        let size = display.size();
+        let palette = self.palette;
+        let pixels = (0..size.height as i32)
+            .flat_map(|y| (0..size.width as i32).map(move |x| Point::new(x, y)))
+            .map(|point| {
+                let color = display.get_pixel(point).unwrap_or(TriColor::White);
+                Pixel(point, palette.color(map(color)))
+            });
+        self.screen.draw_iter(pixels).unwrap();
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Simulator::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::DisplayRotation;
+    use embedded_graphics_simulator::OutputSettingsBuilder;
+    use std::vec::Vec;
+
+    fn display() -> Display {
+        let mut display = Display::new();
+        display.set_rotation(DisplayRotation::Rotate90);
+        display.clear(TriColor::White);
+        display
+            .draw_iter([
+                Pixel(Point::new(0, 0), TriColor::Black),
+                Pixel(Point::new(3, 1), TriColor::Red),
+            ])
+            .unwrap();
+        display
+    }
+
+    #[test]
+    fn screen_shows_drawing_coordinates() {
+        let mut simulator = Simulator::new();
+        simulator.show(&display());
+        let screen = simulator.screen();
+        assert_eq!(Palette::EPAPER.black, screen.get_pixel(Point::new(0, 0)));
+        assert_eq!(Palette::EPAPER.red, screen.get_pixel(Point::new(3, 1)));
+        assert_eq!(Palette::EPAPER.white, screen.get_pixel(Point::new(1, 0)));
+    }
+
+    #[test]
+    fn flash_shows_inverted_black_and_white_first() {
+        let mut simulator = Simulator::with_palette(Palette::PURE);
+        simulator.set_flash(true);
+        let mut frames = Vec::new();
+        simulator.refresh(&display(), |screen| {
+            frames.push([Point::new(0, 0), Point::new(1, 0)].map(|p| screen.get_pixel(p)))
+        });
+        let (black, white) = (Palette::PURE.black, Palette::PURE.white);
+        assert_eq!(
+            [
+                [white, black],
+                [black, black],
+                [white, white],
+                [black, white]
+            ],
+            &frames[..]
+        );
+    }
+
+    #[test]
+    fn output_image_is_scaled() {
+        let mut simulator = Simulator::with_palette(Palette::PURE);
+        simulator.show(&display());
+        let settings = OutputSettingsBuilder::new().scale(2).build();
+        let image = simulator.output_image(&settings);
+        let buffer = image.as_image_buffer();
+        assert_eq!((400, 400), buffer.dimensions());
+        assert_eq!([0, 0, 0], buffer.get_pixel(1, 1).0);
+        assert_eq!([0xFF, 0, 0], buffer.get_pixel(7, 3).0);
+    }
+}
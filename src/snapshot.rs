@@ -0,0 +1,108 @@
+//! Golden image snapshot tests
+//!
+//! [`assert_snapshot`] renders the RAM planes of a [`Display`] and compares them with
+//! `tests/golden/<name>.png` of the crate under test. On a mismatch the actual image and
+//! a diff image, with the differing pixels in blue over a faded copy of the golden one,
+//! are written to `target/snapshots`. Setting `UPDATE_GOLDEN=1` writes the goldens
+//! instead of comparing with them.
+
+use crate::color::TriColor;
+use crate::export::Image;
+use crate::graphics::Display;
+use std::env;
+use std::format;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::vec::Vec;
+
+/// Color of the pixels that differ in a diff image
+const MISMATCH: [u8; 3] = [0x00, 0x00, 0xFF];
+
+/// Compare the panel image of `display` with the golden image `name`, panicking with
+/// the number of mismatched pixels if they differ
+pub fn assert_snapshot(name: &str, display: &Display) {
+    let image = Image::from_planes(display.black_data(), display.red_data(), display.polarity());
+    assert_image_snapshot(name, &image);
+}
+
+/// Compare `image` with the golden image `name`, see [`assert_snapshot`]
+pub fn assert_image_snapshot(name: &str, image: &Image) {
+    let golden = manifest_dir()
+        .join("tests/golden")
+        .join(format!("{name}.png"));
+    if env::var_os("UPDATE_GOLDEN").is_some_and(|value| value != "0") {
+        fs::create_dir_all(golden.parent().unwrap()).unwrap();
+        image.save(&golden).unwrap();
+        return;
+    }
+
+    let expected = match Image::open(&golden) {
+        Ok(expected) => expected,
+        Err(err) => panic!(
+            "cannot read golden image {}: {err}, run with UPDATE_GOLDEN=1 to create it",
+            golden.display()
+        ),
+    };
+    let mismatches = image.mismatches(&expected);
+    if mismatches == 0 {
+        return;
+    }
+
+    let out = target_dir().join("snapshots");
+    let actual = out.join(format!("{name}.actual.ppm"));
+    let diff = out.join(format!("{name}.diff.ppm"));
+    fs::create_dir_all(&out)
+        .and_then(|_| image.save(&actual))
+        .and_then(|_| write_diff(image, &expected, fs::File::create(&diff)?))
+        .unwrap();
+    panic!(
+        "snapshot `{name}` differs from {} in {mismatches} of {} pixels\n  \
+         actual: {}\n  diff: {}\nrun with UPDATE_GOLDEN=1 to accept the change",
+        golden.display(),
+        image.pixels().len(),
+        actual.display(),
+        diff.display()
+    );
+}
+
+/// Write a PPM of `expected` with the pixels that differ in `actual` marked
+fn write_diff(actual: &Image, expected: &Image, mut writer: impl Write) -> io::Result<()> {
+    let (width, height) = (actual.width(), actual.height());
+    write!(writer, "P6\n{width} {height}\n255\n")?;
+    let mut data = Vec::with_capacity(width as usize * height as usize * 3);
+    for y in 0..height {
+        for x in 0..width {
+            let color = actual.pixel(x, y);
+            let golden = expected.pixel(x, y);
+            data.extend(if color == golden {
+                faded(color.unwrap_or(TriColor::White))
+            } else {
+                MISMATCH
+            });
+        }
+    }
+    writer.write_all(&data)
+}
+
+/// Light version of a color, so marked pixels stand out
+fn faded(color: TriColor) -> [u8; 3] {
+    match color {
+        TriColor::Black => [0xA0, 0xA0, 0xA0],
+        TriColor::White => [0xFF, 0xFF, 0xFF],
+        TriColor::Red => [0xFF, 0xB0, 0xB0],
+    }
+}
+
+/// Root of the crate under test, cargo sets this for test runs
+fn manifest_dir() -> PathBuf {
+    env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+}
+
+fn target_dir() -> PathBuf {
+    env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| manifest_dir().join("target"))
+}